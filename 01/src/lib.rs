@@ -1,26 +1,85 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::io;
 use std::io::BufRead;
-use std::collections::HashSet;
+use std::io::Write;
+use std::num::ParseIntError;
 
-/// Parse one line into an int, positive or negative.
-fn parse_int(s: &str) -> i64 {
-    s.parse::<i64>().unwrap()
+/// Error encountered while reading a list of frequency changes.
+#[derive(Debug)]
+pub enum ParseError {
+    /// The input could not be read.
+    Io(io::Error),
+    /// A change could not be parsed as a number.
+    InvalidChange {
+        /// Line of the input where the error was found, starting at 1.
+        line: usize,
+        /// Text that could not be parsed.
+        text: String,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Io(err) => write!(f, "could not read input: {}", err),
+            ParseError::InvalidChange { line, text } => {
+                write!(f, "line {}: invalid frequency change {:?}", line, text)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<io::Error> for ParseError {
+    fn from(err: io::Error) -> Self {
+        ParseError::Io(err)
+    }
+}
+
+/// Parse one change into an int, positive or negative.
+fn parse_int(s: &str) -> Result<i64, ParseIntError> {
+    s.parse::<i64>()
+}
+
+/// Parse one line of the input, which can hold one change or several changes separated by
+/// commas, as in the examples of the puzzle. The line number is only used for errors.
+fn parse_line(line: &str, line_number: usize) -> Result<Vec<i64>, ParseError> {
+    line.split(',')
+        .map(str::trim)
+        // Skip blank lines and trailing commas.
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            parse_int(s).map_err(|_| ParseError::InvalidChange {
+                line: line_number,
+                text: s.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Read the list of changes from a reader, either one per line or separated by commas.
+///
+/// For example:
+/// ```
+/// assert_eq!(vec![1, -2, 3], lib::parse_changes("+1\n-2\n+3\n".as_bytes()).unwrap());
+/// assert_eq!(vec![1, -2, 3], lib::parse_changes("+1, -2, +3".as_bytes()).unwrap());
+/// ```
+pub fn parse_changes<R: BufRead>(reader: R) -> Result<Vec<i64>, ParseError> {
+    let mut changes = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        changes.extend(parse_line(&line?, index + 1)?);
+    }
+    Ok(changes)
 }
 
 /// Read the list of numbers from the standard input.
-pub fn get_list_input() -> Vec<i64> {
+pub fn get_list_input() -> Result<Vec<i64>, ParseError> {
     // Open standard input.
     let stdin = io::stdin();
-    // Get a handle on it.
-    stdin.lock()
-        // Read the input line by line.
-        .lines()
-        // Unwrap each line (makes the program crash if there is an error).
-        .map(|l| l.unwrap())
-        // Parse each line as an int64 (crash on failure).
-        .map(|s| parse_int(&s))
-        // Collect everything into a vector.
-        .collect()
+    // Get a handle on it, and parse it.
+    parse_changes(stdin.lock())
 }
 
 /// Sum the contents of the vector.
@@ -35,26 +94,218 @@ pub fn sum_vector(numbers: &[i64]) -> i64 {
     numbers.iter().sum()
 }
 
-/// Given a list of numbers, find the first partial sum that is reached more than once.
-pub fn find_first_repeated(numbers: &[i64]) -> i64 {
-    // Sum of the numbers seen up to now.
-    let mut sum = 0;
-    // Set of frequencies (partial sums) we have seen so far.
+/// Given a list of numbers, find the first partial sum that is reached more than once, when
+/// cycling over the list forever. Returns None if no partial sum is ever repeated.
+///
+/// Instead of simulating the cycles, we notice that the frequency reached at index i of the
+/// list, after k full passes, is `partial[i] + k * total`, where `partial[i]` is the frequency
+/// reached at index i during the first pass (starting with 0 before any change), and `total` is
+/// the sum of the list. Two such values can only be equal if their partial sums are congruent
+/// modulo the total, so we group the partial sums by residue and only compare neighbours in each
+/// group.
+///
+/// For example:
+/// ```
+/// assert_eq!(Some(2), lib::find_first_repeated(&[1, -2, 3, 1]));
+/// assert_eq!(None, lib::find_first_repeated(&[3, 3]));
+/// ```
+pub fn find_first_repeated(numbers: &[i64]) -> Option<i64> {
+    let total = sum_vector(numbers);
+    // Frequencies reached during the first pass, before each change is applied: the first one is
+    // the initial frequency 0, and the frequency after the last change is `0 + total`, i.e. the
+    // first value of the next pass.
+    let partial_sums = numbers
+        .iter()
+        .scan(0, |sum, number| {
+            let current = *sum;
+            *sum += number;
+            Some(current)
+        })
+        .collect::<Vec<_>>();
+    // If a frequency repeats during the first pass, it is necessarily the first repetition.
     let mut seen = HashSet::new();
-    // We have seen the value 0 (initial frequency).
-    seen.insert(sum);
-    // Iterate over the numbers in the list, in an infinite cycle.
-    for number in numbers.iter().cycle() {
-        sum += number;
-        // If we have seen this new frequency before, we found it!
-        if seen.contains(&sum) {
-            return sum;
+    for sum in &partial_sums {
+        if !seen.insert(*sum) {
+            return Some(*sum);
         }
-        // Add the frequency to the list of those we have seen.
-        seen.insert(sum);
     }
-    // The loop above is infinite, we can't get here.
-    unreachable!();
+    // There is no drift: the second pass starts by reaching 0 again.
+    if total == 0 {
+        return partial_sums.first().cloned();
+    }
+    // Group the partial sums by residue modulo the total drift, keeping track of their index.
+    let mut groups: HashMap<i64, Vec<(i64, usize)>> = HashMap::new();
+    for (index, sum) in partial_sums.iter().enumerate() {
+        groups
+            .entry(sum.rem_euclid(total.abs()))
+            .or_default()
+            .push((*sum, index));
+    }
+    // Candidates: (number of passes, index in the list, repeated frequency). Comparing the tuples
+    // orders them by time of the repetition.
+    let mut candidates = Vec::new();
+    for group in groups.values_mut() {
+        // Sort the group in the direction of the drift: each value is then reached again by its
+        // predecessor in the group, after (difference / total) passes.
+        group.sort_unstable_by_key(|&(sum, _)| sum * total.signum());
+        for pair in group.windows(2) {
+            let (start, index) = pair[0];
+            let (target, _) = pair[1];
+            candidates.push(((target - start) / total, index, target));
+        }
+    }
+    candidates
+        .into_iter()
+        .min()
+        .map(|(_, _, frequency)| frequency)
+}
+
+/// Position in the cycled list of changes: the change at `index` (starting at 0) during the
+/// `pass`-th pass over the list (starting at 0).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub pass: usize,
+    pub index: usize,
+}
+
+/// A frequency that was reached a second (or third, ...) time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repetition {
+    pub frequency: i64,
+    /// Change after which the frequency was first reached, or None for the initial frequency 0.
+    pub first: Option<Position>,
+    /// Change after which the frequency was reached again.
+    pub repeated: Position,
+}
+
+/// Lowest and highest frequencies reached during one pass over the list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PassRange {
+    pub pass: usize,
+    pub min: i64,
+    pub max: i64,
+}
+
+/// One step of the walk over the cycled list: the frequency after applying a change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub position: Position,
+    pub change: i64,
+    pub frequency: i64,
+    /// Whether this frequency was already reached before.
+    pub repeated: bool,
+}
+
+/// Trace of the frequencies reached when applying a list of changes a fixed number of times.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrequencyTrace {
+    /// Every step of the walk, in order.
+    pub steps: Vec<Step>,
+    /// Every frequency reached more than once, in the order in which they were repeated.
+    pub repetitions: Vec<Repetition>,
+    /// Range of the frequencies for each pass.
+    pub pass_ranges: Vec<PassRange>,
+}
+
+impl FrequencyTrace {
+    /// Return the first repetition, i.e. the one found by `find_first_repeated` if the trace is
+    /// long enough.
+    pub fn first_repetition(&self) -> Option<&Repetition> {
+        self.repetitions.first()
+    }
+
+    /// Write the trace as CSV, one row per step, starting with the initial frequency (which has
+    /// no pass, index nor change).
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "step,pass,index,change,frequency,repeated")?;
+        writeln!(writer, "0,,,,0,false")?;
+        for (step_number, step) in self.steps.iter().enumerate() {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                step_number + 1,
+                step.position.pass,
+                step.position.index,
+                step.change,
+                step.frequency,
+                step.repeated
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compute the range of frequencies reached during each of the first `passes` passes. Each pass
+/// is the first one shifted by the sum of the list, so we only need to walk the list once.
+fn compute_pass_ranges(numbers: &[i64], passes: usize) -> Vec<PassRange> {
+    if numbers.is_empty() {
+        return Vec::new();
+    }
+    let total = sum_vector(numbers);
+    // Frequencies reached during the first pass.
+    let frequencies = numbers.iter().scan(0, |sum, number| {
+        *sum += number;
+        Some(*sum)
+    });
+    let (min, max) = frequencies.fold((i64::MAX, i64::MIN), |(min, max), f| {
+        (min.min(f), max.max(f))
+    });
+    (0..passes)
+        .map(|pass| PassRange {
+            pass,
+            min: min + pass as i64 * total,
+            max: max + pass as i64 * total,
+        })
+        .collect()
+}
+
+/// Apply the list of changes `passes` times, recording every step and every repeated frequency.
+///
+/// For example:
+/// ```
+/// let trace = lib::trace_frequencies(&[1, -2, 3, 1], 3);
+/// let first = trace.first_repetition().unwrap();
+/// assert_eq!(lib::find_first_repeated(&[1, -2, 3, 1]), Some(first.frequency));
+/// assert_eq!(lib::Position { pass: 1, index: 1 }, first.repeated);
+/// ```
+pub fn trace_frequencies(numbers: &[i64], passes: usize) -> FrequencyTrace {
+    // Position at which each frequency was first reached.
+    let mut first_seen: HashMap<i64, Option<Position>> = HashMap::new();
+    first_seen.insert(0, None);
+    let mut steps = Vec::with_capacity(numbers.len() * passes);
+    let mut repetitions = Vec::new();
+    let mut frequency = 0;
+    for pass in 0..passes {
+        for (index, change) in numbers.iter().enumerate() {
+            frequency += change;
+            let position = Position { pass, index };
+            let repeated = match first_seen.get(&frequency) {
+                Some(first) => {
+                    repetitions.push(Repetition {
+                        frequency,
+                        first: *first,
+                        repeated: position,
+                    });
+                    true
+                }
+                None => {
+                    first_seen.insert(frequency, Some(position));
+                    false
+                }
+            };
+            steps.push(Step {
+                position,
+                change: *change,
+                frequency,
+                repeated,
+            });
+        }
+    }
+    FrequencyTrace {
+        steps,
+        repetitions,
+        pass_ranges: compute_pass_ranges(numbers, passes),
+    }
 }
 
 #[cfg(test)]
@@ -63,15 +314,91 @@ mod tests {
 
     #[test]
     fn test_first_repeated() {
-        assert_eq!(0, find_first_repeated(&[1, -1]));
-        assert_eq!(10, find_first_repeated(&[3, 3, 4, -2, -4]));
-        assert_eq!(5, find_first_repeated(&[-6, 3, 8, 5, -6]));
-        assert_eq!(14, find_first_repeated(&[7, 7, -2, -7, -4]));
+        assert_eq!(Some(0), find_first_repeated(&[1, -1]));
+        assert_eq!(Some(10), find_first_repeated(&[3, 3, 4, -2, -4]));
+        assert_eq!(Some(5), find_first_repeated(&[-6, 3, 8, 5, -6]));
+        assert_eq!(Some(14), find_first_repeated(&[7, 7, -2, -7, -4]));
+        assert_eq!(Some(2), find_first_repeated(&[1, -2, 3, 1]));
+    }
+
+    #[test]
+    fn test_first_repeated_never() {
+        assert_eq!(None, find_first_repeated(&[]));
+        assert_eq!(None, find_first_repeated(&[1]));
+        assert_eq!(None, find_first_repeated(&[3, 3]));
+        assert_eq!(None, find_first_repeated(&[-5, 2]));
     }
 
     #[test]
     fn test_parse_int() {
-        assert_eq!(3, parse_int("+3"));
-        assert_eq!(-44, parse_int("-44"));
+        assert_eq!(Ok(3), parse_int("+3"));
+        assert_eq!(Ok(-44), parse_int("-44"));
+        assert!(parse_int("4a").is_err());
+    }
+
+    #[test]
+    fn test_parse_changes() {
+        assert_eq!(vec![1, -1], parse_changes("+1\n\n-1\n".as_bytes()).unwrap());
+        assert_eq!(
+            vec![3, 3, 4, -2, -4],
+            parse_changes("+3, +3, +4,\n-2, -4".as_bytes()).unwrap()
+        );
+        match parse_changes("+1\n-2\n+x3\n".as_bytes()) {
+            Err(ParseError::InvalidChange { line, text }) => {
+                assert_eq!(3, line);
+                assert_eq!("+x3", text);
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_trace_frequencies() {
+        let trace = trace_frequencies(&[1, -2, 3, 1], 2);
+        assert_eq!(8, trace.steps.len());
+        assert_eq!(
+            vec![Repetition {
+                frequency: 2,
+                first: Some(Position { pass: 0, index: 2 }),
+                repeated: Position { pass: 1, index: 1 },
+            }],
+            trace.repetitions
+        );
+        assert_eq!(
+            vec![
+                PassRange { pass: 0, min: -1, max: 3 },
+                PassRange { pass: 1, min: 2, max: 6 },
+            ],
+            trace.pass_ranges
+        );
+    }
+
+    #[test]
+    fn test_trace_initial_frequency() {
+        let trace = trace_frequencies(&[1, -1], 2);
+        assert_eq!(
+            Some(&Repetition {
+                frequency: 0,
+                first: None,
+                repeated: Position { pass: 0, index: 1 },
+            }),
+            trace.first_repetition()
+        );
+        assert_eq!(3, trace.repetitions.len());
+    }
+
+    #[test]
+    fn test_trace_csv() {
+        let mut output = Vec::new();
+        trace_frequencies(&[1, -1], 1)
+            .write_csv(&mut output)
+            .unwrap();
+        assert_eq!(
+            "step,pass,index,change,frequency,repeated\n\
+             0,,,,0,false\n\
+             1,0,0,1,1,false\n\
+             2,0,1,-1,0,true\n",
+            String::from_utf8(output).unwrap()
+        );
     }
 }
@@ -3,11 +3,18 @@ use lib::get_list_input;
 
 fn main() {
     // Read the list of numbers from the standard input.
-    let numbers = get_list_input();
+    let numbers = match get_list_input() {
+        Ok(numbers) => numbers,
+        Err(err) => {
+            println!("Error reading input: {}", err);
+            std::process::exit(2);
+        }
+    };
     // Compute the sum.
     println!("Sum: {}", lib::sum_vector(&numbers));
-    println!(
-        "First repeated frequency: {}",
-        lib::find_first_repeated(&numbers)
-    );
+    if let Some(frequency) = lib::find_first_repeated(&numbers) {
+        println!("First repeated frequency: {}", frequency);
+    } else {
+        println!("No frequency is ever reached twice");
+    }
 }
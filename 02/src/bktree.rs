@@ -0,0 +1,298 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+/// Distance used to compare IDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// Number of positions at which the IDs differ. When the lengths differ, the extra characters
+    /// of the longest ID count as differences.
+    Hamming,
+    /// Minimum number of single character insertions, deletions and substitutions to go from one
+    /// ID to the other.
+    Levenshtein,
+}
+
+impl Metric {
+    /// Compute the distance between two IDs.
+    ///
+    /// For example:
+    /// ```
+    /// use lib::Metric;
+    /// assert_eq!(2, Metric::Hamming.distance("abcde", "axcye"));
+    /// assert_eq!(5, Metric::Hamming.distance("abcde", "bcdef"));
+    /// assert_eq!(2, Metric::Levenshtein.distance("abcde", "bcdef"));
+    /// ```
+    pub fn distance(self, first: &str, second: &str) -> usize {
+        match self {
+            Metric::Hamming => hamming(first, second),
+            Metric::Levenshtein => levenshtein(first, second),
+        }
+    }
+}
+
+/// Hamming distance, extended to IDs of different lengths.
+fn hamming(first: &str, second: &str) -> usize {
+    let first_length = first.chars().count();
+    let second_length = second.chars().count();
+    let differences = first
+        .chars()
+        .zip(second.chars())
+        .filter(|(a, b)| a != b)
+        .count();
+    differences + first_length.max(second_length) - first_length.min(second_length)
+}
+
+/// Levenshtein (edit) distance, computed one row of the table at a time.
+fn levenshtein(first: &str, second: &str) -> usize {
+    let second = second.chars().collect::<Vec<_>>();
+    // Distance between the prefix of `first` seen so far and each prefix of `second`.
+    let mut row = (0..=second.len()).collect::<Vec<_>>();
+    for (i, a) in first.chars().enumerate() {
+        let mut next_row = Vec::with_capacity(row.len());
+        next_row.push(i + 1);
+        for (j, b) in second.iter().enumerate() {
+            let substitution = row[j] + if a == *b { 0 } else { 1 };
+            let deletion = row[j + 1] + 1;
+            let insertion = next_row[j] + 1;
+            next_row.push(substitution.min(deletion).min(insertion));
+        }
+        row = next_row;
+    }
+    row[second.len()]
+}
+
+/// Node of the BK-tree: an ID, and its children indexed by their distance to it.
+#[derive(Debug)]
+struct Node {
+    id: String,
+    children: HashMap<usize, Node>,
+}
+
+/// Metric index over IDs (Burkhard-Keller tree), to find the IDs closest to a query.
+///
+/// Each child of a node is at a given distance of it, so thanks to the triangle inequality we only
+/// need to explore the children whose distance is close to the distance between the node and the
+/// query.
+#[derive(Debug)]
+pub struct BkTree {
+    metric: Metric,
+    root: Option<Node>,
+    size: usize,
+}
+
+impl BkTree {
+    /// Create an empty tree using the given metric.
+    pub fn new(metric: Metric) -> Self {
+        Self {
+            metric,
+            root: None,
+            size: 0,
+        }
+    }
+
+    /// Create a tree containing all the given IDs.
+    pub fn from_ids(metric: Metric, ids: &[String]) -> Self {
+        let mut tree = Self::new(metric);
+        ids.iter().for_each(|id| tree.insert(id));
+        tree
+    }
+
+    /// Number of distinct IDs in the tree.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Return whether the tree contains no IDs.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Add an ID to the tree. Duplicate IDs are only stored once.
+    pub fn insert(&mut self, id: &str) {
+        let mut node = match &mut self.root {
+            Some(root) => root,
+            None => {
+                self.root = Some(Node {
+                    id: id.to_string(),
+                    children: HashMap::new(),
+                });
+                self.size = 1;
+                return;
+            }
+        };
+        loop {
+            let distance = self.metric.distance(&node.id, id);
+            if distance == 0 {
+                return;
+            }
+            match node.children.entry(distance) {
+                Entry::Occupied(child) => node = child.into_mut(),
+                Entry::Vacant(child) => {
+                    child.insert(Node {
+                        id: id.to_string(),
+                        children: HashMap::new(),
+                    });
+                    self.size += 1;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Find all the IDs within `max_distance` of the query, sorted by distance then by ID.
+    ///
+    /// For example:
+    /// ```
+    /// use lib::{BkTree, Metric};
+    /// let ids = ["abcde", "fghij", "fguij", "axcye"];
+    /// let ids = ids.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    /// let tree = BkTree::from_ids(Metric::Hamming, &ids);
+    /// assert_eq!(vec![("fghij", 1), ("fguij", 1)], tree.find_within("fgxij", 1));
+    /// ```
+    pub fn find_within(&self, query: &str, max_distance: usize) -> Vec<(&str, usize)> {
+        let mut result = Vec::new();
+        // Nodes left to explore.
+        let mut stack = self.root.iter().collect::<Vec<_>>();
+        while let Some(node) = stack.pop() {
+            let distance = self.metric.distance(&node.id, query);
+            if distance <= max_distance {
+                result.push((node.id.as_str(), distance));
+            }
+            stack.extend(
+                node.children
+                    .iter()
+                    .filter(|(d, _)| **d + max_distance >= distance && **d <= distance + max_distance)
+                    .map(|(_, child)| child),
+            );
+        }
+        result.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(b.0)));
+        result
+    }
+
+    /// Find the `count` IDs closest to the query, sorted by distance then by ID. Useful to suggest
+    /// IDs when the query is not found.
+    ///
+    /// For example:
+    /// ```
+    /// use lib::{BkTree, Metric};
+    /// let ids = ["abcde", "fghij", "fguij", "axcye"];
+    /// let ids = ids.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    /// let tree = BkTree::from_ids(Metric::Levenshtein, &ids);
+    /// assert_eq!(vec![("abcde", 1)], tree.nearest("bcde", 1));
+    /// ```
+    pub fn nearest(&self, query: &str, count: usize) -> Vec<(&str, usize)> {
+        // Best IDs so far, kept sorted.
+        let mut best: Vec<(&str, usize)> = Vec::with_capacity(count + 1);
+        if count == 0 {
+            return best;
+        }
+        let mut stack = self.root.iter().collect::<Vec<_>>();
+        while let Some(node) = stack.pop() {
+            let distance = self.metric.distance(&node.id, query);
+            let candidate = (node.id.as_str(), distance);
+            let position = best
+                .binary_search_by(|b| b.1.cmp(&distance).then(b.0.cmp(candidate.0)))
+                .unwrap_or_else(|p| p);
+            if position < count {
+                best.insert(position, candidate);
+                best.truncate(count);
+            }
+            // Until we have enough IDs, every node needs to be explored; after that, only the
+            // ones that could be at least as close as the worst of the best IDs.
+            let radius = if best.len() < count {
+                usize::MAX
+            } else {
+                best[count - 1].1
+            };
+            stack.extend(
+                node.children
+                    .iter()
+                    .filter(|(d, _)| {
+                        d.saturating_add(radius) >= distance && **d <= distance.saturating_add(radius)
+                    })
+                    .map(|(_, child)| child),
+            );
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_tree(metric: Metric) -> BkTree {
+        let ids = &[
+            "abcde", "fghij", "klmno", "pqrst", "fguij", "axcye", "wvxyz", "fghij",
+        ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        BkTree::from_ids(metric, ids)
+    }
+
+    #[test]
+    fn test_hamming() {
+        assert_eq!(0, hamming("abc", "abc"));
+        assert_eq!(1, hamming("fghij", "fguij"));
+        assert_eq!(2, hamming("abc", "a"));
+        assert_eq!(1, hamming("é", "e"));
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(0, levenshtein("", ""));
+        assert_eq!(3, levenshtein("", "abc"));
+        assert_eq!(3, levenshtein("kitten", "sitting"));
+        assert_eq!(1, levenshtein("fghij", "fgij"));
+        assert_eq!(1, levenshtein("café", "cafe"));
+    }
+
+    #[test]
+    fn test_insert() {
+        let tree = example_tree(Metric::Hamming);
+        assert_eq!(7, tree.len());
+        assert!(!tree.is_empty());
+        assert!(BkTree::new(Metric::Levenshtein).is_empty());
+    }
+
+    #[test]
+    fn test_find_within() {
+        let tree = example_tree(Metric::Hamming);
+        assert_eq!(vec![("fghij", 0), ("fguij", 1)], tree.find_within("fghij", 1));
+        assert_eq!(vec![("abcde", 1), ("axcye", 1)], tree.find_within("abcye", 1));
+        assert!(tree.find_within("zzzzz", 3).is_empty());
+        let tree = example_tree(Metric::Levenshtein);
+        assert_eq!(vec![("fghij", 1), ("fguij", 2)], tree.find_within("fhij", 2));
+    }
+
+    #[test]
+    fn test_nearest() {
+        let tree = example_tree(Metric::Levenshtein);
+        assert_eq!(vec![("fghij", 1), ("fguij", 1)], tree.nearest("fgxij", 2));
+        assert_eq!(vec![("abcde", 1)], tree.nearest("abde", 1));
+        assert_eq!(7, tree.nearest("", 10).len());
+        assert!(tree.nearest("abcde", 0).is_empty());
+        assert!(BkTree::new(Metric::Hamming).nearest("abcde", 3).is_empty());
+    }
+
+    #[test]
+    fn test_nearest_matches_brute_force() {
+        let ids = (0..200u32)
+            .map(|i| format!("{:08b}", i.wrapping_mul(2_654_435_761) >> 24))
+            .collect::<Vec<_>>();
+        for metric in &[Metric::Hamming, Metric::Levenshtein] {
+            let tree = BkTree::from_ids(*metric, &ids);
+            for query in &["00000000", "10101010", "1111", "0110011001"] {
+                let mut expected = ids
+                    .iter()
+                    .map(|id| (id.as_str(), metric.distance(id, query)))
+                    .collect::<Vec<_>>();
+                expected.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(b.0)));
+                expected.dedup();
+                expected.truncate(5);
+                assert_eq!(expected, tree.nearest(query, 5));
+            }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+/// Two IDs that differ in at most k positions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NearMatch {
+    /// Index of the first ID in the input list.
+    pub first: usize,
+    /// Index of the second ID in the input list (always greater than `first`).
+    pub second: usize,
+    /// Positions (in characters) at which the two IDs differ.
+    pub differences: Vec<usize>,
+}
+
+/// Result of a near-duplicate search.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NearDuplicates {
+    /// All the pairs within the requested distance, sorted by index.
+    pub matches: Vec<NearMatch>,
+    /// Indices of the IDs whose length differs from the length of the first ID. They are only
+    /// compared with IDs of the same length.
+    pub other_lengths: Vec<usize>,
+}
+
+/// Bounds (in characters) of the k + 1 segments an ID of the given length is split into. The
+/// segments are as even as possible, some of them might be empty for very short IDs.
+fn segment_bounds(length: usize, segments: usize) -> Vec<(usize, usize)> {
+    (0..segments)
+        .map(|s| (s * length / segments, (s + 1) * length / segments))
+        .collect()
+}
+
+/// List the positions at which two IDs of the same length differ, or None if there are more than
+/// `max_distance` of them.
+fn differences(first: &[char], second: &[char], max_distance: usize) -> Option<Vec<usize>> {
+    let mut result = Vec::new();
+    for (i, (a, b)) in first.iter().zip(second).enumerate() {
+        if a != b {
+            if result.len() == max_distance {
+                return None;
+            }
+            result.push(i);
+        }
+    }
+    Some(result)
+}
+
+/// Find all the pairs within distance k among IDs that all have the same length.
+///
+/// By the pigeonhole principle, if we cut the IDs in k + 1 segments, two IDs with at most k
+/// differences have at least one identical segment. So for each segment, we index the IDs by the
+/// content of that segment, and only compare the IDs that share it. To report each pair only
+/// once, we only keep the pair for the first segment on which they agree.
+fn find_within_length(ids: &[(usize, Vec<char>)], max_distance: usize, out: &mut Vec<NearMatch>) {
+    let length = match ids.first() {
+        Some((_, id)) => id.len(),
+        None => return,
+    };
+    let bounds = segment_bounds(length, max_distance + 1);
+    for (segment, &(start, end)) in bounds.iter().enumerate() {
+        // Position in `ids` of all the IDs, by the content of the current segment.
+        let mut buckets: HashMap<&[char], Vec<usize>> = HashMap::new();
+        for (position, (_, id)) in ids.iter().enumerate() {
+            buckets.entry(&id[start..end]).or_default().push(position);
+        }
+        for bucket in buckets.values() {
+            for (i, &first) in bucket.iter().enumerate() {
+                for &second in &bucket[i + 1..] {
+                    let (first_index, first_id) = &ids[first];
+                    let (second_index, second_id) = &ids[second];
+                    // Skip the pair if it was already found with a previous segment.
+                    if bounds[..segment]
+                        .iter()
+                        .any(|&(s, e)| first_id[s..e] == second_id[s..e])
+                    {
+                        continue;
+                    }
+                    if let Some(differences) = differences(first_id, second_id, max_distance) {
+                        out.push(NearMatch {
+                            first: *first_index,
+                            second: *second_index,
+                            differences,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Find all the pairs of IDs that differ in at most `max_distance` positions (Hamming distance).
+///
+/// IDs can only be compared with IDs of the same length, the ones that don't have the same length
+/// as the first ID are listed in the result.
+///
+/// For example:
+/// ```
+/// let ids = ["abcde", "axcye", "fghij", "fguij", "abc"];
+/// let ids = ids.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+/// let result = lib::find_near_duplicates(&ids, 2);
+/// assert_eq!(2, result.matches.len());
+/// assert_eq!(vec![1, 3], result.matches[0].differences);
+/// assert_eq!(vec![4], result.other_lengths);
+/// ```
+pub fn find_near_duplicates(lines: &[String], max_distance: usize) -> NearDuplicates {
+    // Group the IDs by length, keeping their index in the input.
+    let mut by_length: HashMap<usize, Vec<(usize, Vec<char>)>> = HashMap::new();
+    for (index, line) in lines.iter().enumerate() {
+        let id = line.chars().collect::<Vec<_>>();
+        by_length.entry(id.len()).or_default().push((index, id));
+    }
+    let mut result = NearDuplicates::default();
+    for ids in by_length.values() {
+        find_within_length(ids, max_distance, &mut result.matches);
+    }
+    result.matches.sort_by_key(|m| (m.first, m.second));
+    if let Some(first) = lines.first() {
+        let length = first.chars().count();
+        result.other_lengths = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.chars().count() != length)
+            .map(|(i, _)| i)
+            .collect();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_strings(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_segment_bounds() {
+        assert_eq!(vec![(0, 2), (2, 5)], segment_bounds(5, 2));
+        assert_eq!(vec![(0, 0), (0, 1), (1, 2)], segment_bounds(2, 3));
+    }
+
+    #[test]
+    fn test_differences() {
+        let a = "abcde".chars().collect::<Vec<_>>();
+        let b = "axcyz".chars().collect::<Vec<_>>();
+        assert_eq!(Some(vec![1, 3, 4]), differences(&a, &b, 3));
+        assert_eq!(None, differences(&a, &b, 2));
+        assert_eq!(Some(vec![]), differences(&a, &a, 0));
+    }
+
+    #[test]
+    fn test_find_near_duplicates() {
+        let ids = to_strings(&[
+            "abcde", "fghij", "klmno", "pqrst", "fguij", "axcye", "wvxyz",
+        ]);
+        assert_eq!(
+            vec![NearMatch {
+                first: 1,
+                second: 4,
+                differences: vec![2],
+            }],
+            find_near_duplicates(&ids, 1).matches
+        );
+        let result = find_near_duplicates(&ids, 2);
+        assert_eq!(
+            vec![
+                NearMatch {
+                    first: 0,
+                    second: 5,
+                    differences: vec![1, 3],
+                },
+                NearMatch {
+                    first: 1,
+                    second: 4,
+                    differences: vec![2],
+                },
+            ],
+            result.matches
+        );
+        assert!(result.other_lengths.is_empty());
+    }
+
+    #[test]
+    fn test_find_near_duplicates_other_lengths() {
+        let ids = to_strings(&["abcd", "abc", "abd", "abce", "é"]);
+        let result = find_near_duplicates(&ids, 1);
+        assert_eq!(vec![1, 2, 4], result.other_lengths);
+        assert_eq!(
+            vec![
+                NearMatch {
+                    first: 0,
+                    second: 3,
+                    differences: vec![3],
+                },
+                NearMatch {
+                    first: 1,
+                    second: 2,
+                    differences: vec![2],
+                },
+            ],
+            result.matches
+        );
+    }
+
+    #[test]
+    fn test_find_near_duplicates_short_ids() {
+        // With more allowed differences than characters, every pair matches.
+        let ids = to_strings(&["ab", "cd", "ef"]);
+        assert_eq!(3, find_near_duplicates(&ids, 3).matches.len());
+    }
+}
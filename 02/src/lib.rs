@@ -1,109 +1,139 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::option::Option;
 use std::string::String;
 
-/// Size of the alphabet.
-const ALPHABET_SIZE: usize = 26;
-
-/// This structure helps to count the number of pairs and triplets in a string.
-/// It assumes that the string is composed only of lowercase letters between 'a'
-/// and 'z'.
-struct LetterCounter {
-    /// This is a histogram of how many times we've seen each letter, by their
-    /// index in the alphabet.
-    histogram: [i32; ALPHABET_SIZE],
-    /// Number of (exact) pairs of characters.
-    number_of_pairs: i32,
-    /// Number of (exact) triplets of characters.
-    number_of_triplets: i32,
+pub use crate::bktree::{BkTree, Metric};
+pub use crate::hamming::{find_near_duplicates, NearDuplicates, NearMatch};
+
+mod bktree;
+mod hamming;
+
+/// This structure helps to count how many letters appear exactly n times in a
+/// string, for any n. Any `char` is accepted as a letter.
+#[derive(Debug, Default)]
+pub struct LetterCounter {
+    /// This is a histogram of how many times we've seen each letter.
+    histogram: HashMap<char, usize>,
+    /// For each count, the number of letters that we've seen exactly that many
+    /// times. Counts with no letters are removed.
+    multiplicities: HashMap<usize, usize>,
 }
 
 impl LetterCounter {
-    /// Create a new LetterCount with everything initialized to 0.
-    fn new() -> Self {
-        Self {
-            histogram: [0; ALPHABET_SIZE],
-            number_of_pairs: 0,
-            number_of_triplets: 0,
-        }
+    /// Create a new LetterCounter with no letters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a LetterCounter with all the letters of the ID.
+    pub fn from_id(id: &str) -> Self {
+        let mut counter = Self::new();
+        id.chars().for_each(|l| counter.add_letter(l));
+        counter
     }
 
-    /// Add a letter to our histogram, and update the counts of pairs and
-    /// triplets.
-    fn add_letter(&mut self, letter: char) {
-        // Index of the letter in the alphabet.
-        let index = (letter as usize) - ('a' as usize);
+    /// Add a letter to our histogram, and update the multiplicities.
+    pub fn add_letter(&mut self, letter: char) {
         // Reference to the relevant cell in the histogram.
-        let cell = &mut self.histogram[index];
-        // If the letter was already a pair or a triple, it's not anymore (since
-        // we're adding one count).
-        match cell {
-            2 => self.number_of_pairs -= 1,
-            3 => self.number_of_triplets -= 1,
-            _ => (),
-        };
+        let cell = self.histogram.entry(letter).or_insert(0);
+        // The letter is not seen exactly `cell` times anymore (since we're
+        // adding one count).
+        if *cell > 0 {
+            let previous = self.multiplicities.get_mut(cell).unwrap();
+            *previous -= 1;
+            if *previous == 0 {
+                self.multiplicities.remove(cell);
+            }
+        }
         *cell += 1;
-        // If the letter is now a pair or a triple, add it to the count.
-        match cell {
-            2 => self.number_of_pairs += 1,
-            3 => self.number_of_triplets += 1,
-            _ => (),
-        };
+        // The letter is now seen exactly `cell` times.
+        *self.multiplicities.entry(*cell).or_insert(0) += 1;
     }
 
-    /// Return whether we have at least one (exact) pair of letters.
-    fn has_exactly_two(&self) -> bool {
-        self.number_of_pairs > 0
+    /// Return the number of times we've seen the letter.
+    pub fn count(&self, letter: char) -> usize {
+        self.histogram.get(&letter).cloned().unwrap_or(0)
     }
 
-    /// Return whether we have at least one (exact) triplet of letters.
-    fn has_exactly_three(&self) -> bool {
-        self.number_of_triplets > 0
+    /// Return whether at least one letter appears exactly n times. Since any
+    /// character is a letter, this is always false for n = 0.
+    pub fn has_exactly(&self, n: usize) -> bool {
+        self.multiplicities.contains_key(&n)
     }
 }
 
-/// Check whether a string has pairs and/or triplets. It returns 2 values,
-/// whether there are pairs, and whether there are triplets.
-fn count_pairs_and_triplets(line: &str) -> (bool, bool) {
-    let mut counter = LetterCounter::new();
-    line.chars().for_each(|l| counter.add_letter(l));
-    (counter.has_exactly_two(), counter.has_exactly_three())
+/// Detailed checksum of a list of IDs, for a set of multiplicities.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumReport {
+    /// Multiplicities that were checked, in the order they were given.
+    pub multiplicities: Vec<usize>,
+    /// For each multiplicity, the number of IDs with a letter appearing exactly
+    /// that many times.
+    pub counts: Vec<usize>,
+    /// For each ID, whether it has a letter appearing exactly n times, for each
+    /// multiplicity n (in the same order as `multiplicities`).
+    pub per_id: Vec<Vec<bool>>,
+    /// The checksum: the product of the counts.
+    pub value: i64,
+}
+
+/// Compute the checksum of a list of IDs over a set of multiplicities: for
+/// each multiplicity n, count the number of IDs with a letter appearing exactly
+/// n times, and multiply these counts together.
+///
+/// For example:
+/// ```
+/// let ids = ["abcdef", "bababc", "abbcde", "abcccd"];
+/// let ids = ids.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+/// let report = lib::checksum_with_multiplicities(&ids, &[2, 3]);
+/// assert_eq!(vec![2, 2], report.counts);
+/// assert_eq!(vec![true, true], report.per_id[1]);
+/// assert_eq!(4, report.value);
+/// ```
+pub fn checksum_with_multiplicities(lines: &[String], multiplicities: &[usize]) -> ChecksumReport {
+    // For each line, check each multiplicity.
+    let per_id = lines
+        .iter()
+        .map(|l| {
+            let counter = LetterCounter::from_id(l);
+            multiplicities
+                .iter()
+                .map(|n| counter.has_exactly(*n))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    // For each multiplicity, count the lines that have it.
+    let counts = (0..multiplicities.len())
+        .map(|i| per_id.iter().filter(|id| id[i]).count())
+        .collect::<Vec<_>>();
+    let value = counts.iter().map(|c| *c as i64).product();
+    ChecksumReport {
+        multiplicities: multiplicities.to_vec(),
+        counts,
+        per_id,
+        value,
+    }
 }
 
 /// Compute the checksum of a list of IDs: this is the number of IDs with an
 /// (exact) pair times the number of IDs with an exact triplet.
 pub fn checksum(lines: &[String]) -> i64 {
-    // Call count_pairs_and_triplets on every line. We get a list of pairs of
-    // bool.
-    let counts = lines.iter().map(|l| count_pairs_and_triplets(l));
-    // Count the number of elements with true in the first spot.
-    let number_of_pairs = counts.clone().filter(|t| t.0).count();
-    // Count the number of elements with true in the second spot.
-    let number_of_triplets = counts.filter(|t| t.1).count();
-    (number_of_pairs * number_of_triplets) as i64
-}
-
-/// Consider each line without the nth letter (index), and try to find
-/// duplicates. Returns the first duplicate line, or None if there is none.
-fn find_matching_ids_without_letter(lines: &[String], index: usize) -> Option<String> {
-    let mut seen: HashSet<String> = HashSet::new();
-    for id in lines {
-        let new_string: String = id.char_indices()
-            .filter_map(|(i, c)| if i == index { None } else { Some(c) })
-            .collect();
-        if seen.contains(&new_string) {
-            return Some(new_string);
-        }
-        seen.insert(new_string);
-    }
-    None
+    checksum_with_multiplicities(lines, &[2, 3]).value
 }
 
 /// Find matching ids. Two IDs are matching if they differ only by 1 character.
+/// Returns the characters the first matching pair has in common, or None if
+/// there is no such pair (in particular when there are no IDs at all).
 pub fn find_matching_ids(lines: &[String]) -> Option<String> {
-    (0..lines[0].len())
-        .filter_map(|i| find_matching_ids_without_letter(&lines, i))
-        .next()
+    let near = find_near_duplicates(lines, 1);
+    let found = near.matches.iter().find(|m| m.differences.len() == 1)?;
+    let index = found.differences[0];
+    let common = lines[found.first]
+        .chars()
+        .enumerate()
+        .filter_map(|(i, c)| if i == index { None } else { Some(c) })
+        .collect();
+    Some(common)
 }
 
 #[cfg(test)]
@@ -113,40 +143,56 @@ mod tests {
     #[test]
     fn test_letter_counter() {
         let mut counter = LetterCounter::new();
-        assert_eq!(0, counter.histogram[0]);
-        assert_eq!(0, counter.number_of_pairs);
-        assert_eq!(0, counter.number_of_triplets);
+        assert_eq!(0, counter.count('a'));
+        assert!(!counter.has_exactly(1));
         counter.add_letter('a');
-        assert_eq!(1, counter.histogram[0]);
-        assert_eq!(0, counter.number_of_pairs);
+        assert_eq!(1, counter.count('a'));
+        assert!(counter.has_exactly(1));
+        assert!(!counter.has_exactly(2));
         counter.add_letter('a');
-        assert_eq!(2, counter.histogram[0]);
-        assert_eq!(1, counter.number_of_pairs);
-        assert_eq!(true, counter.has_exactly_two());
-        assert_eq!(false, counter.has_exactly_three());
+        assert_eq!(2, counter.count('a'));
+        assert!(!counter.has_exactly(1));
+        assert!(counter.has_exactly(2));
+        assert!(!counter.has_exactly(3));
         counter.add_letter('a');
-        assert_eq!(3, counter.histogram[0]);
-        assert_eq!(0, counter.number_of_pairs);
-        assert_eq!(1, counter.number_of_triplets);
-        assert_eq!(false, counter.has_exactly_two());
-        assert_eq!(true, counter.has_exactly_three());
+        assert_eq!(3, counter.count('a'));
+        assert!(!counter.has_exactly(2));
+        assert!(counter.has_exactly(3));
         counter.add_letter('a');
-        assert_eq!(4, counter.histogram[0]);
-        assert_eq!(0, counter.number_of_pairs);
-        assert_eq!(0, counter.number_of_triplets);
+        assert_eq!(4, counter.count('a'));
+        assert!(!counter.has_exactly(2));
+        assert!(!counter.has_exactly(3));
+        assert!(counter.has_exactly(4));
         counter.add_letter('c');
-        assert_eq!(1, counter.histogram[2]);
+        assert_eq!(1, counter.count('c'));
+        assert!(counter.has_exactly(1));
+        assert!(!counter.has_exactly(0));
+    }
+
+    #[test]
+    fn test_letter_counter_any_char() {
+        let counter = LetterCounter::from_id("AbA9é9é€");
+        assert_eq!(2, counter.count('A'));
+        assert_eq!(0, counter.count('a'));
+        assert_eq!(2, counter.count('é'));
+        assert!(counter.has_exactly(1));
+        assert!(counter.has_exactly(2));
+        assert!(!counter.has_exactly(3));
     }
 
     #[test]
-    fn test_count_pairs_and_triplets() {
-        assert_eq!((false, false), count_pairs_and_triplets(&"abcdef"));
-        assert_eq!((true, true), count_pairs_and_triplets(&"bababc"));
-        assert_eq!((true, false), count_pairs_and_triplets(&"abbcde"));
-        assert_eq!((false, true), count_pairs_and_triplets(&"abcccd"));
-        assert_eq!((true, false), count_pairs_and_triplets(&"aabcdd"));
-        assert_eq!((true, false), count_pairs_and_triplets(&"abcdee"));
-        assert_eq!((false, true), count_pairs_and_triplets(&"ababab"));
+    fn test_pairs_and_triplets() {
+        let check = |id| {
+            let counter = LetterCounter::from_id(id);
+            (counter.has_exactly(2), counter.has_exactly(3))
+        };
+        assert_eq!((false, false), check("abcdef"));
+        assert_eq!((true, true), check("bababc"));
+        assert_eq!((true, false), check("abbcde"));
+        assert_eq!((false, true), check("abcccd"));
+        assert_eq!((true, false), check("aabcdd"));
+        assert_eq!((true, false), check("abcdee"));
+        assert_eq!((false, true), check("ababab"));
     }
 
     #[test]
@@ -159,31 +205,31 @@ mod tests {
             "aabcdd",
             "abcdee",
             "ababab",
-        ].into_iter()
+        ].iter()
             .map(|s| s.to_string())
             .collect::<Vec<_>>();
         assert_eq!(12, checksum(lines));
     }
 
     #[test]
-    fn test_find_matching_ids_without_letter() {
-        let lines = &[
-            "abcde",
-            "fghij",
-            "klmno",
-            "pqrst",
-            "fguij",
-            "axcye",
-            "wvxyz",
-        ].into_iter()
+    fn test_checksum_with_multiplicities() {
+        let lines = &["aabbb", "ABBccc", "xyzzzz", "ééé"]
+            .iter()
             .map(|s| s.to_string())
             .collect::<Vec<_>>();
-        assert_eq!(None, find_matching_ids_without_letter(lines, 0));
-        assert_eq!(None, find_matching_ids_without_letter(lines, 1));
+        let report = checksum_with_multiplicities(lines, &[1, 3, 4]);
+        assert_eq!(vec![2, 3, 1], report.counts);
         assert_eq!(
-            Some("fgij".to_string()),
-            find_matching_ids_without_letter(lines, 2)
+            vec![
+                vec![false, true, false],
+                vec![true, true, false],
+                vec![true, false, true],
+                vec![false, true, false],
+            ],
+            report.per_id
         );
+        assert_eq!(6, report.value);
+        assert_eq!(1, checksum_with_multiplicities(lines, &[]).value);
     }
 
     #[test]
@@ -196,9 +242,19 @@ mod tests {
             "fguij",
             "axcye",
             "wvxyz",
-        ].into_iter()
+        ].iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(Some("fgij".to_string()), find_matching_ids(lines));
+    }
+
+    #[test]
+    fn test_find_matching_ids_no_match() {
+        assert_eq!(None, find_matching_ids(&[]));
+        let lines = &["abc", "abc", "xyz"]
+            .iter()
             .map(|s| s.to_string())
             .collect::<Vec<_>>();
-        assert_eq!("fgij", find_matching_ids(lines));
+        assert_eq!(None, find_matching_ids(lines));
     }
 }
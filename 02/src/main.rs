@@ -4,9 +4,7 @@ use std::io::BufRead;
 
 /// Solution for the 2nd day of the advent of code 2018.
 ///
-/// This solution assumes that all the ids are composed only of lowercase letters, and that they
-/// are all the same length.
-
+/// This solution assumes that all the ids are the same length.
 fn main() {
     let stdin = io::stdin();
     // Read the lines of the input.
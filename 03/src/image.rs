@@ -0,0 +1,135 @@
+use std::io;
+use std::io::Write;
+
+use crate::{far_corner, x_axis, y_axis, Board, State};
+
+/// Color of the square inches claimed by a single claim.
+const CLAIMED_COLOR: [u8; 3] = [160, 160, 160];
+/// Color of the square inches claimed by several claims.
+const OVERLAPPING_COLOR: [u8; 3] = [255, 0, 0];
+/// Color of the square inches of the claims that don't overlap any other.
+const ISOLATED_COLOR: [u8; 3] = [0, 255, 0];
+/// Color of the square inches that nobody claimed.
+const UNCLAIMED_COLOR: [u8; 3] = [0, 0, 0];
+
+impl Board {
+    /// Size of the image: up to the furthest claim corner on each axis.
+    fn image_size(&self) -> (usize, usize) {
+        let claims = self.claims.values().map(|(c, _)| c);
+        let width = claims.clone().map(|c| far_corner(c, &x_axis)).max();
+        let height = claims.map(|c| far_corner(c, &y_axis)).max();
+        (width.unwrap_or(0), height.unwrap_or(0))
+    }
+
+    /// Write the board as a binary grayscale PGM image, where the intensity of each pixel is the
+    /// number of claims on the square inch (x from left to right, y from top to bottom).
+    pub fn write_pgm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let (width, height) = self.image_size();
+        let max_count = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|coord| self.cells[coord].1.len())
+            .max()
+            .unwrap_or(0)
+            .max(1)
+            .min(usize::from(u16::MAX));
+        write!(writer, "P5\n{} {}\n{}\n", width, height, max_count)?;
+        let mut row = Vec::with_capacity(2 * width);
+        for y in 0..height {
+            row.clear();
+            for x in 0..width {
+                let count = self.cells[(x, y)].1.len().min(max_count);
+                // Samples take 2 bytes (big-endian) when the maximum doesn't fit in one.
+                if max_count > 255 {
+                    row.extend_from_slice(&(count as u16).to_be_bytes());
+                } else {
+                    row.push(count as u8);
+                }
+            }
+            writer.write_all(&row)?;
+        }
+        Ok(())
+    }
+
+    /// Write the board as a binary PPM image: overlapping square inches are red, the claims that
+    /// don't overlap any other are green, and the other claimed square inches are gray.
+    pub fn write_ppm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let (width, height) = self.image_size();
+        write!(writer, "P6\n{} {}\n255\n", width, height)?;
+        let mut row = Vec::with_capacity(3 * width);
+        for y in 0..height {
+            row.clear();
+            for x in 0..width {
+                let (state, claims) = &self.cells[(x, y)];
+                let color = match state {
+                    State::Unclaimed => UNCLAIMED_COLOR,
+                    State::Overlapping => OVERLAPPING_COLOR,
+                    State::Claimed if self.is_isolated(claims[0]) => ISOLATED_COLOR,
+                    State::Claimed => CLAIMED_COLOR,
+                };
+                row.extend_from_slice(&color);
+            }
+            writer.write_all(&row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Claim;
+
+    fn example_board() -> Board {
+        let mut board = Board::new(0, 0);
+        board.add_claim(Claim {
+            id: 1,
+            coordinates: (0, 1),
+            size: (2, 1),
+        });
+        board.add_claim(Claim {
+            id: 2,
+            coordinates: (1, 0),
+            size: (1, 2),
+        });
+        board.add_claim(Claim {
+            id: 3,
+            coordinates: (2, 0),
+            size: (1, 1),
+        });
+        board
+    }
+
+    #[test]
+    fn test_write_pgm() {
+        let mut output = Vec::new();
+        example_board().write_pgm(&mut output).unwrap();
+        let mut expected = b"P5\n3 2\n2\n".to_vec();
+        expected.extend_from_slice(&[0, 1, 1, 1, 2, 0]);
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let mut output = Vec::new();
+        example_board().write_ppm(&mut output).unwrap();
+        let mut expected = b"P6\n3 2\n255\n".to_vec();
+        for color in &[
+            UNCLAIMED_COLOR,
+            CLAIMED_COLOR,
+            ISOLATED_COLOR,
+            CLAIMED_COLOR,
+            OVERLAPPING_COLOR,
+            UNCLAIMED_COLOR,
+        ] {
+            expected.extend_from_slice(color);
+        }
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_empty_board() {
+        let mut output = Vec::new();
+        Board::new(5, 5).write_pgm(&mut output).unwrap();
+        assert_eq!(b"P5\n0 0\n1\n".to_vec(), output);
+    }
+}
@@ -1,39 +1,107 @@
 #[macro_use]
 extern crate nom;
 
+use std::fmt;
 use std::string::String;
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 use itertools::Itertools;
 
-use crate::parse::Claim;
 use crate::vec2d::Vec2D;
 
+pub use crate::parse::{Claim, ClaimError, Expected};
+pub use crate::query::{ClaimIndex, ClaimOverlap};
+pub use crate::sweep::Overlaps;
+
+mod image;
 mod parse;
+mod query;
+mod sweep;
 mod vec2d;
 
 
-/// Parse all the lines as claims.
-fn parse_lines(lines: &[String]) -> Vec<Claim> {
-    lines.iter().map(|l| parse::claim(l)).collect()
+/// Error while parsing one of the lines of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Line of the error, starting at 1.
+    pub line: usize,
+    pub error: ClaimError,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, {}", self.line, self.error)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse one line as a claim, keeping track of the line number for errors.
+fn parse_line(index: usize, line: &str) -> Result<Claim, ParseError> {
+    parse::claim(line).map_err(|error| ParseError {
+        line: index + 1,
+        error,
+    })
+}
+
+/// Parse all the lines as claims, stopping at the first error.
+///
+/// For example:
+/// ```
+/// let lines = ["#1 @ 1,3: 4x4", "#12 @ 3,4 5x5"];
+/// let lines = lines.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+/// let error = lib::parse_lines(&lines).unwrap_err();
+/// assert_eq!("line 2, column 11: expected ':', found \"5x5\"", error.to_string());
+/// ```
+pub fn parse_lines(lines: &[String]) -> Result<Vec<Claim>, ParseError> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, l)| parse_line(i, l))
+        .collect()
+}
+
+/// Parse all the lines as claims, skipping the invalid ones. Returns the valid claims, and the
+/// errors for the other lines.
+pub fn parse_lines_lenient(lines: &[String]) -> (Vec<Claim>, Vec<ParseError>) {
+    let mut claims = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        match parse_line(i, line) {
+            Ok(claim) => claims.push(claim),
+            Err(error) => errors.push(error),
+        }
+    }
+    (claims, errors)
 }
 
 /// State of a square inch of the fabric.
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum State {
-    UNCLAIMED,
-    CLAIMED,
+    Unclaimed,
+    Claimed,
     /// Claimed by more than 1 elf.
-    OVERLAPPING,
+    Overlapping,
 }
 
 /// Claim a cell. It returns the new state of the cell, whether the cell is overlapping, and
 /// whether it started overlapping.
 fn claim_cell(state: State) -> (State, bool, bool) {
     match state {
-        State::UNCLAIMED => (State::CLAIMED, false, true),
-        State::CLAIMED => (State::OVERLAPPING, true, true),
-        State::OVERLAPPING => (State::OVERLAPPING, true, false),
+        State::Unclaimed => (State::Claimed, false, false),
+        State::Claimed => (State::Overlapping, true, true),
+        State::Overlapping => (State::Overlapping, true, false),
+    }
+}
+
+/// Release a cell, given the number of claims left on it. It returns the new state of the cell,
+/// and whether it stopped overlapping.
+fn release_cell(state: State, remaining_claims: usize) -> (State, bool) {
+    match (state, remaining_claims) {
+        (State::Overlapping, 1) => (State::Claimed, true),
+        (State::Overlapping, _) => (State::Overlapping, false),
+        (State::Claimed, _) => (State::Unclaimed, false),
+        (State::Unclaimed, _) => unreachable!("Releasing an unclaimed cell"),
     }
 }
 
@@ -45,73 +113,201 @@ fn y_axis(coord: (usize, usize)) -> usize {
     coord.1
 }
 
-fn far_corner(claim: &Claim, axis: &Fn((usize, usize)) -> usize) -> usize {
-    axis(claim.coordinates) + axis(claim.size)
+fn far_corner(claim: &Claim, axis: &dyn Fn((usize, usize)) -> usize) -> usize {
+    axis(claim.far_corner())
 }
 
 /// Find the maximum coordinate of the `claims`, along the dimension provided by `axis`.
-fn find_max_coordinate(claims: &[Claim], axis: &Fn((usize, usize)) -> usize) -> usize {
+fn find_max_coordinate(claims: &[Claim], axis: &dyn Fn((usize, usize)) -> usize) -> usize {
     claims.iter().map(|c| far_corner(c, axis)).max().unwrap_or(
         0,
     )
 }
 
-struct Board {
+/// Iterate over the coordinates of all the square inches of the claim.
+fn claim_cells(claim: &Claim) -> impl Iterator<Item = (usize, usize)> {
+    (x_axis(claim.coordinates)..far_corner(claim, &x_axis))
+        .cartesian_product(y_axis(claim.coordinates)..far_corner(claim, &y_axis))
+}
+
+/// Dense representation of the fabric, with the claims covering each square inch. Claims can be
+/// added, removed or updated, and the overlapping area and the isolated claims are kept up to date.
+///
+/// The board grows as needed to fit the claims, so it is only suitable for small fabrics.
+#[derive(Debug)]
+pub struct Board {
     cells: Vec2D<(State, Vec<usize>)>,
     overlapping: usize,
-    non_overlapping_ids: HashSet<usize>,
+    /// Claims on the board by ID, with the number of their cells shared with other claims.
+    claims: HashMap<usize, (Claim, usize)>,
 }
 
 impl Board {
-    fn new(max_x: usize, max_y: usize) -> Self {
+    /// Create an empty board, with room for claims up to the given coordinates.
+    pub fn new(max_x: usize, max_y: usize) -> Self {
         Self {
-            cells: Vec2D::from_fn(max_x, max_y, &|| (State::UNCLAIMED, vec![])),
+            cells: Vec2D::from_fn(max_x, max_y, &|| (State::Unclaimed, vec![])),
             overlapping: 0,
-            non_overlapping_ids: HashSet::new(),
+            claims: HashMap::new(),
         }
     }
 
-    fn process_cell(&mut self, coord: (usize, usize)) -> bool {
+    /// Make sure the board has room for the claim, doubling its size as needed.
+    fn reserve(&mut self, claim: &Claim) {
+        let (height, width) = (self.cells.height(), self.cells.width());
+        let max_x = far_corner(claim, &x_axis);
+        let max_y = far_corner(claim, &y_axis);
+        if max_x > height || max_y > width {
+            self.cells.resize(
+                max_x.max(height.saturating_mul(2)),
+                max_y.max(width.saturating_mul(2)),
+                &|| (State::Unclaimed, vec![]),
+            );
+        }
+    }
+
+    /// Add a claim to a cell. Returns whether the cell is overlapping.
+    fn process_cell(&mut self, coord: (usize, usize), id: usize) -> bool {
         let (status, is_overlapping, starts_overlapping) = claim_cell(self.cells[coord].0);
         self.cells[coord].0 = status;
         if starts_overlapping {
             self.overlapping += 1;
-            self.non_overlapping_ids.remove(&self.cells[coord].1[0]);
+            // The claim that was alone on the cell now shares it.
+            self.claims.get_mut(&self.cells[coord].1[0]).unwrap().1 += 1;
         }
+        self.cells[coord].1.push(id);
         is_overlapping
     }
 
-    fn process_claim(&mut self, claim: &Claim) {
-        let mut is_overlapping = false;
-        (x_axis(claim.coordinates)..far_corner(&claim, &x_axis))
-            .cartesian_product(y_axis(claim.coordinates)..far_corner(&claim, &y_axis))
-            .for_each(|coord| {
-                is_overlapping = self.process_cell(coord);
-                self.cells[coord].1.push(claim.id);
-            });
-        if !is_overlapping {
-            self.non_overlapping_ids.insert(claim.id);
+    /// Remove a claim from a cell.
+    fn release_cell(&mut self, coord: (usize, usize), id: usize) {
+        let (state, claims) = &mut self.cells[coord];
+        claims.retain(|c| *c != id);
+        let (status, stops_overlapping) = release_cell(*state, claims.len());
+        *state = status;
+        if stops_overlapping {
+            self.overlapping -= 1;
+            // The claim left on the cell doesn't share it anymore.
+            self.claims.get_mut(&self.cells[coord].1[0]).unwrap().1 -= 1;
         }
     }
 
+    /// Add a claim to the board. If there is already a claim with the same ID, it is replaced.
+    pub fn add_claim(&mut self, claim: Claim) {
+        self.remove_claim(claim.id);
+        self.reserve(&claim);
+        self.claims.insert(claim.id, (claim.clone(), 0));
+        let mut shared_cells = 0;
+        for coord in claim_cells(&claim) {
+            if self.process_cell(coord, claim.id) {
+                shared_cells += 1;
+            }
+        }
+        self.claims.get_mut(&claim.id).unwrap().1 = shared_cells;
+    }
+
+    /// Withdraw the claim with the given ID from the board, and return it.
+    pub fn remove_claim(&mut self, id: usize) -> Option<Claim> {
+        let (claim, _) = self.claims.remove(&id)?;
+        claim_cells(&claim).for_each(|coord| self.release_cell(coord, id));
+        Some(claim)
+    }
+
+    /// Replace the claim with the same ID with the new one, and return the old one.
+    pub fn update_claim(&mut self, claim: Claim) -> Option<Claim> {
+        let old = self.remove_claim(claim.id);
+        self.add_claim(claim);
+        old
+    }
+
+    /// Area claimed by more than one claim.
+    pub fn overlapping_area(&self) -> usize {
+        self.overlapping
+    }
+
+    /// Return whether the claim with the given ID is on the board and doesn't overlap any other.
+    pub fn is_isolated(&self, id: usize) -> bool {
+        self.claims.get(&id).map(|(_, shared)| *shared) == Some(0)
+    }
+
+    /// Sorted IDs of the claims that don't overlap any other.
+    pub fn isolated_ids(&self) -> Vec<usize> {
+        let mut ids = self
+            .claims
+            .iter()
+            .filter(|(_, (_, shared))| *shared == 0)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
+    }
+
     fn get_result(self) -> (usize, Option<usize>) {
-        (
-            self.overlapping,
-            self.non_overlapping_ids.into_iter().next(),
-        )
+        (self.overlapping, self.isolated_ids().into_iter().next())
     }
 }
 
+/// Find the area of overlap between the claims, as well as the ID of the first claim that doesn't
+/// overlap with any other.
+///
+/// This allocates a cell for each square inch up to the furthest claim corner, so it is only
+/// suitable for small fabrics. See `find_overlaps` for large coordinates.
+pub fn find_overlapping_area(claims: &[Claim]) -> (usize, Option<usize>) {
+    build_board(claims).get_result()
+}
 
-/// Find the area of overlap between the claims defined by `lines`, as well as the ID of the first
-/// claim that doesn't overlap with any other.
-pub fn find_overlapping_area(lines: &[String]) -> (usize, Option<usize>) {
-    let claims = parse_lines(lines);
-    let max_x = find_max_coordinate(&claims, &x_axis);
-    let max_y = find_max_coordinate(&claims, &y_axis);
+/// Number of square inches of the board that `build_board` would allocate for the claims, or None
+/// if it doesn't even fit in a `usize`.
+///
+/// For example:
+/// ```
+/// let lines = ["#1 @ 1,3: 4x4", "#2 @ 3,1: 4x4"];
+/// let lines = lines.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+/// let claims = lib::parse_lines(&lines).unwrap();
+/// assert_eq!(Some(49), lib::board_area(&claims));
+/// ```
+pub fn board_area(claims: &[Claim]) -> Option<usize> {
+    find_max_coordinate(claims, &x_axis).checked_mul(find_max_coordinate(claims, &y_axis))
+}
+
+/// Build a board with all the claims.
+pub fn build_board(claims: &[Claim]) -> Board {
+    let max_x = find_max_coordinate(claims, &x_axis);
+    let max_y = find_max_coordinate(claims, &y_axis);
     let mut board = Board::new(max_x, max_y);
-    claims.iter().for_each(|c| board.process_claim(c));
-    board.get_result()
+    claims.iter().for_each(|c| board.add_claim(c.clone()));
+    board
+}
+
+/// Find the area of overlap between the claims, as well as the IDs of all the
+/// claims that don't overlap with any other. Unlike `find_overlapping_area`, this doesn't depend
+/// on the size of the fabric.
+///
+/// For example:
+/// ```
+/// let lines = ["#1 @ 1,3: 4x4", "#2 @ 3,1: 4x4", "#3 @ 5000000,5000000: 2x2"];
+/// let lines = lines.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+/// let overlaps = lib::find_overlaps(&lib::parse_lines(&lines).unwrap());
+/// assert_eq!(4, overlaps.area);
+/// assert_eq!(vec![3], overlaps.isolated_ids);
+/// ```
+pub fn find_overlaps(claims: &[Claim]) -> Overlaps {
+    sweep::sweep_overlaps(claims)
+}
+
+/// Build an index over the claims, to query the claims at a position and the overlaps between
+/// them.
+///
+/// For example:
+/// ```
+/// let lines = ["#1 @ 1,3: 4x4", "#2 @ 3,1: 4x4", "#3 @ 5,5: 2x2"];
+/// let lines = lines.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+/// let index = lib::build_index(&lib::parse_lines(&lines).unwrap());
+/// assert_eq!(vec![1, 2], index.claims_at((3, 4)));
+/// assert_eq!(vec![vec![1, 2], vec![3]], index.connected_components());
+/// ```
+pub fn build_index(claims: &[Claim]) -> ClaimIndex {
+    ClaimIndex::new(claims.to_vec())
 }
 
 
@@ -128,7 +324,7 @@ mod tests {
         ];
         assert_eq!(
             parse_lines(&lines),
-            vec![
+            Ok(vec![
                 Claim {
                     id: 1,
                     coordinates: (1, 3),
@@ -144,7 +340,51 @@ mod tests {
                     coordinates: (5, 5),
                     size: (2, 2),
                 },
-            ]
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_lines_errors() {
+        let lines: Vec<String> = vec![
+            "#1 @ 1,3: 4x4".to_string(),
+            "#12 @ 3,4 5x5".to_string(),
+            "#3 @ 5,5: 2x2".to_string(),
+            "#4 @ 99999999999999999999999,5: 2x2".to_string(),
+            format!("#5 @ {},5: 2x2", usize::MAX),
+        ];
+        let first_error = ParseError {
+            line: 2,
+            error: ClaimError {
+                column: 11,
+                expected: Expected::Colon,
+                found: "5x5".to_string(),
+            },
+        };
+        assert_eq!(Err(first_error.clone()), parse_lines(&lines));
+        let (claims, errors) = parse_lines_lenient(&lines);
+        assert_eq!(vec![1, 3], claims.iter().map(|c| c.id).collect::<Vec<_>>());
+        assert_eq!(
+            vec![
+                first_error,
+                ParseError {
+                    line: 4,
+                    error: ClaimError {
+                        column: 6,
+                        expected: Expected::Number,
+                        found: "99999999999999999999999,5: 2x2".to_string(),
+                    },
+                },
+                ParseError {
+                    line: 5,
+                    error: ClaimError {
+                        column: lines[4].len() - 2,
+                        expected: Expected::SizeInRange,
+                        found: "2x2".to_string(),
+                    },
+                },
+            ],
+            errors
         );
     }
 
@@ -155,6 +395,97 @@ mod tests {
             "#2 @ 3,1: 4x4".to_string(),
             "#3 @ 5,5: 2x2".to_string(),
         ];
-        assert_eq!(find_overlapping_area(&lines), 4);
+        assert_eq!(find_overlapping_area(&parse_lines(&lines).unwrap()), (4, Some(3)));
+    }
+
+    #[test]
+    fn test_sweep_matches_board() {
+        // Pseudo-random claims on a small fabric.
+        let mut seed: usize = 42;
+        let mut next = |modulo: usize| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 33) % modulo
+        };
+        let lines = (1..=60)
+            .map(|id| {
+                format!(
+                    "#{} @ {},{}: {}x{}",
+                    id,
+                    next(80),
+                    next(80),
+                    next(15) + 1,
+                    next(15) + 1
+                )
+            })
+            .collect::<Vec<_>>();
+        let claims = parse_lines(&lines).unwrap();
+        let board = build_board(&claims);
+        assert_eq!(
+            Overlaps {
+                area: board.overlapping_area(),
+                isolated_ids: board.isolated_ids(),
+            },
+            find_overlaps(&claims)
+        );
+    }
+
+    fn claim(id: usize, coordinates: (usize, usize), size: (usize, usize)) -> Claim {
+        Claim {
+            id,
+            coordinates,
+            size,
+        }
+    }
+
+    #[test]
+    fn test_release_cell() {
+        assert_eq!((State::Unclaimed, false), release_cell(State::Claimed, 0));
+        assert_eq!((State::Claimed, true), release_cell(State::Overlapping, 1));
+        assert_eq!((State::Overlapping, false), release_cell(State::Overlapping, 2));
+    }
+
+    #[test]
+    fn test_board_add_remove_update() {
+        let mut board = Board::new(0, 0);
+        board.add_claim(claim(1, (1, 3), (4, 4)));
+        board.add_claim(claim(2, (3, 1), (4, 4)));
+        board.add_claim(claim(3, (5, 5), (2, 2)));
+        assert_eq!(4, board.overlapping_area());
+        assert_eq!(vec![3], board.isolated_ids());
+        // Claim 3 now overlaps claim 2 on 1 square inch, and 1 on none.
+        assert_eq!(Some(claim(3, (5, 5), (2, 2))), board.update_claim(claim(3, (6, 4), (2, 2))));
+        assert_eq!(5, board.overlapping_area());
+        assert_eq!(Vec::<usize>::new(), board.isolated_ids());
+        // Without claim 2, the others are isolated.
+        assert_eq!(Some(claim(2, (3, 1), (4, 4))), board.remove_claim(2));
+        assert_eq!(None, board.remove_claim(2));
+        assert_eq!(0, board.overlapping_area());
+        assert_eq!(vec![1, 3], board.isolated_ids());
+        assert!(board.is_isolated(1));
+        assert!(!board.is_isolated(2));
+        // Adding a claim far away grows the board.
+        board.add_claim(claim(4, (100, 200), (3, 3)));
+        board.add_claim(claim(5, (101, 201), (3, 3)));
+        assert_eq!(4, board.overlapping_area());
+        assert_eq!(vec![1, 3], board.isolated_ids());
+    }
+
+    #[test]
+    fn test_board_triple_overlap() {
+        let mut board = Board::new(10, 10);
+        board.add_claim(claim(1, (0, 0), (2, 2)));
+        board.add_claim(claim(2, (0, 0), (2, 2)));
+        board.add_claim(claim(3, (1, 1), (2, 2)));
+        assert_eq!(4, board.overlapping_area());
+        board.remove_claim(1);
+        assert_eq!(1, board.overlapping_area());
+        board.remove_claim(2);
+        assert_eq!(0, board.overlapping_area());
+        assert_eq!(vec![3], board.isolated_ids());
+        // Re-adding a claim with an existing ID replaces it.
+        board.add_claim(claim(3, (5, 5), (1, 1)));
+        board.add_claim(claim(3, (5, 5), (1, 1)));
+        assert_eq!(0, board.overlapping_area());
+        assert_eq!(vec![3], board.isolated_ids());
     }
 }
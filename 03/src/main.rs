@@ -1,15 +1,58 @@
 extern crate lib;
+use std::fs::File;
 use std::io;
-use std::io::BufRead;
+use std::io::{BufRead, BufWriter};
 use std::string::String;
 
+/// Largest number of square inches rendered with `--output`: the board keeps a cell per square
+/// inch, which doesn't fit in memory for arbitrary claims.
+const MAX_IMAGE_AREA: usize = 1 << 24;
+
+/// Usage: day_03 [--output <image.pgm|image.ppm>] < input
+///
+/// With `--output`, the fabric is also rendered as an image: a grayscale heatmap of the number of
+/// claims per square inch for a .pgm file, or a color map of the overlaps for a .ppm file.
 fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let output = match args.as_slice() {
+        [_] => None,
+        [_, flag, path] if flag == "--output" => Some(path.clone()),
+        _ => {
+            println!("Usage: {} [--output <image.pgm|image.ppm>] < input", args[0]);
+            std::process::exit(2);
+        }
+    };
     let stdin = io::stdin();
     let lines: Vec<String> = stdin.lock().lines().map(|l| l.unwrap()).collect();
-    let (area, non_overlapping_claim) = lib::find_overlapping_area(&lines);
-    println!("Area: {}", area);
-    match non_overlapping_claim {
-        None => println!("No/several non-overlapping claims"),
-        Some(c) => println!("Non-overlapping claim: {}", c),
+    // Skip the invalid lines, and report them all.
+    let (claims, errors) = lib::parse_lines_lenient(&lines);
+    for error in &errors {
+        println!("Skipping invalid claim: {}", error);
+    }
+    let overlaps = lib::find_overlaps(&claims);
+    println!("Area: {}", overlaps.area);
+    match overlaps.isolated_ids.as_slice() {
+        [c] => println!("Non-overlapping claim: {}", c),
+        _ => println!("No/several non-overlapping claims"),
+    }
+    if let Some(path) = output {
+        match lib::board_area(&claims) {
+            Some(area) if area <= MAX_IMAGE_AREA => {}
+            _ => {
+                println!("The fabric is too large to be rendered as an image");
+                std::process::exit(2);
+            }
+        }
+        let board = lib::build_board(&claims);
+        let file = BufWriter::new(File::create(&path).expect("Could not create output file"));
+        let result = if path.ends_with(".ppm") {
+            board.write_ppm(file)
+        } else {
+            board.write_pgm(file)
+        };
+        if let Err(err) = result {
+            println!("Error writing image: {}", err);
+            std::process::exit(2);
+        }
     }
 }
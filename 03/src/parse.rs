@@ -1,52 +1,195 @@
-use nom::digit;
 use nom::types::CompleteStr;
+use nom::{digit, eof, space0, Context, Err, ErrorKind};
+use std::fmt;
 use std::str::FromStr;
 
 /// Represents a claim made by an elf.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Claim {
     pub id: usize,
     pub coordinates: (usize, usize),
     pub size: (usize, usize),
 }
 
-/// Parse an int (unsigned).
-named!(usize <&str, usize>,
-   map!(map!(digit, FromStr::from_str), Result::unwrap)
+impl Claim {
+    /// Coordinates just past the bottom-right corner of the claim.
+    ///
+    /// The parser only returns claims whose far corner is in range, so this panics only for
+    /// claims built by hand.
+    pub fn far_corner(&self) -> (usize, usize) {
+        far_corner(self.coordinates, self.size)
+            .expect("The far corner of the claim is out of range")
+    }
+
+    /// Return whether the square inch at `point` is part of the claim.
+    pub fn contains(&self, point: (usize, usize)) -> bool {
+        let far = self.far_corner();
+        (self.coordinates.0..far.0).contains(&point.0)
+            && (self.coordinates.1..far.1).contains(&point.1)
+    }
+
+    /// Area shared by the claim and the rectangle at `coordinates` of the given `size`. It saturates
+    /// at `usize::MAX` for areas that don't fit.
+    pub fn intersection_area(&self, coordinates: (usize, usize), size: (usize, usize)) -> usize {
+        let far = self.far_corner();
+        let width = far
+            .0
+            .min(coordinates.0.saturating_add(size.0))
+            .saturating_sub(self.coordinates.0.max(coordinates.0));
+        let height = far
+            .1
+            .min(coordinates.1.saturating_add(size.1))
+            .saturating_sub(self.coordinates.1.max(coordinates.1));
+        width.saturating_mul(height)
+    }
+
+    /// Area shared by the two claims.
+    pub fn overlap_area(&self, other: &Claim) -> usize {
+        self.intersection_area(other.coordinates, other.size)
+    }
+}
+
+/// Coordinates just past the bottom-right corner of a claim, or None if they don't fit.
+fn far_corner(coordinates: (usize, usize), size: (usize, usize)) -> Option<(usize, usize)> {
+    Some((
+        coordinates.0.checked_add(size.0)?,
+        coordinates.1.checked_add(size.1)?,
+    ))
+}
+
+/// Token that the parser expected when it failed. It is used as custom nom error code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    Hash = 1,
+    Number,
+    At,
+    Comma,
+    Colon,
+    Times,
+    /// A size that keeps the far corner of the claim in range.
+    SizeInRange,
+    EndOfLine,
+}
+
+impl Expected {
+    fn from_code(code: u32) -> Option<Self> {
+        [
+            Expected::Hash,
+            Expected::Number,
+            Expected::At,
+            Expected::Comma,
+            Expected::Colon,
+            Expected::Times,
+            Expected::SizeInRange,
+            Expected::EndOfLine,
+        ]
+        .iter()
+        .cloned()
+        .find(|e| *e as u32 == code)
+    }
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            Expected::Hash => "'#'",
+            Expected::Number => "a number",
+            Expected::At => "'@'",
+            Expected::Comma => "','",
+            Expected::Colon => "':'",
+            Expected::Times => "'x'",
+            Expected::SizeInRange => "a size that keeps the claim in range",
+            Expected::EndOfLine => "end of line",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+/// Error while parsing a claim: where it failed, and what was expected there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaimError {
+    /// Column of the error, in characters, starting at 1.
+    pub column: usize,
+    pub expected: Expected,
+    /// Rest of the line, starting at the error.
+    pub found: String,
+}
+
+impl fmt::Display for ClaimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "column {}: expected {}, found {:?}",
+            self.column, self.expected, self.found
+        )
+    }
+}
+
+// Parse an int (unsigned). Fails on numbers that don't fit.
+named!(usize <CompleteStr, usize>,
+   return_error!(ErrorKind::Custom(Expected::Number as u32),
+       map_res!(digit, |s: CompleteStr| FromStr::from_str(s.0)))
 );
 
-/// Parse the identifier of the claim.
-named!(id <&str, usize>,
-    preceded!(tag_s!("#"), usize)
+// Parse a single character, failing with the given expected token.
+macro_rules! expect_char (
+  ($i:expr, $c:expr, $expected:expr) => (
+    return_error!($i, ErrorKind::Custom($expected as u32), char!($c))
+  );
 );
 
-/// Parse the coordinates of the top-left corner (format: "1,3").
-named!(coordinates <&str, (usize, usize)>,
-  separated_pair!(usize, char!(','), usize)
+// Parse the identifier of the claim.
+named!(id <CompleteStr, usize>,
+    preceded!(expect_char!('#', Expected::Hash), usize)
 );
 
-/// Parse the size of the claim (format: "1x3").
-named!(size <&str, (usize, usize)>,
-  separated_pair!(usize, char!('x'), usize)
+// Parse the coordinates of the top-left corner (format: "1,3").
+named!(coordinates <CompleteStr, (usize, usize)>,
+  separated_pair!(usize, expect_char!(',', Expected::Comma), usize)
 );
 
-/// Parse the whole claim.
-/// e.g. "#1 @ 1,3: 4x4 "
-/// Note: the string must end with a space or EOF.
-named!(claim_impl <&str, Claim>,
+// Parse the size of the claim (format: "1x3").
+named!(size <CompleteStr, (usize, usize)>,
+  separated_pair!(usize, expect_char!('x', Expected::Times), usize)
+);
+
+// Parse the whole claim.
+// e.g. "#1 @ 1,3: 4x4"
+named!(claim_impl <CompleteStr, Claim>,
     do_parse!(
+        space0 >>
         id: id >>
-        ws!(char!('@')) >>
+        space0 >>
+        expect_char!('@', Expected::At) >>
+        space0 >>
         coordinates: coordinates >>
-        ws!(char!(':')) >>
-        size: size >>
-        (Claim {id: id, coordinates: coordinates, size: size})
+        space0 >>
+        expect_char!(':', Expected::Colon) >>
+        space0 >>
+        // The size is parsed once on its own, so that its errors are reported as they are.
+        peek!(size) >>
+        size: return_error!(ErrorKind::Custom(Expected::SizeInRange as u32),
+            verify!(size, |s| far_corner(coordinates, s).is_some())) >>
+        space0 >>
+        return_error!(ErrorKind::Custom(Expected::EndOfLine as u32), eof!()) >>
+        (Claim {id, coordinates, size})
     )
 );
 
-/// Convenience function to parse a claim from a string.
-pub fn claim(input: &str) -> Claim {
-    claim_impl(&CompleteStr(input)).unwrap().1
+/// Parse a claim from a string, e.g. "#1 @ 1,3: 4x4".
+pub fn claim(input: &str) -> Result<Claim, ClaimError> {
+    match claim_impl(CompleteStr(input)) {
+        Ok((_, claim)) => Ok(claim),
+        Err(Err::Failure(Context::Code(rest, ErrorKind::Custom(code)))) => {
+            let consumed = &input[..input.len() - rest.len()];
+            Err(ClaimError {
+                column: consumed.chars().count() + 1,
+                expected: Expected::from_code(code).unwrap(),
+                found: rest.0.to_string(),
+            })
+        }
+        Err(err) => unreachable!("All the parsers have a custom error: {:?}", err),
+    }
 }
 
 #[cfg(test)]
@@ -54,18 +197,80 @@ mod tests {
     use super::*;
     #[test]
     fn test_usize() {
-        assert_eq!(usize("123 ").unwrap().1, 123);
+        assert_eq!(usize(CompleteStr("123")).unwrap().1, 123);
+    }
+
+    #[test]
+    fn test_usize_overflow() {
+        assert!(usize(CompleteStr("123456789012345678901234567890")).is_err());
     }
 
     #[test]
     fn test_claim() {
         assert_eq!(
-            claim_impl("#1 @ 1,3: 4x4 ").unwrap().1,
+            claim_impl(CompleteStr("#1 @ 1,3: 4x4")).unwrap().1,
             Claim {
                 id: 1,
                 coordinates: (1, 3),
                 size: (4, 4),
             }
         );
+        assert_eq!(
+            claim(" #12@3,4:5x5 "),
+            Ok(Claim {
+                id: 12,
+                coordinates: (3, 4),
+                size: (5, 5),
+            })
+        );
+    }
+
+    #[test]
+    fn test_intersection_area() {
+        let claim = Claim {
+            id: 1,
+            coordinates: (1, 3),
+            size: (4, 4),
+        };
+        assert!(claim.contains((1, 3)));
+        assert!(!claim.contains((5, 3)));
+        assert_eq!(claim.intersection_area((3, 1), (4, 4)), 4);
+        assert_eq!(claim.intersection_area((5, 5), (2, 2)), 0);
+        let huge = Claim {
+            id: 2,
+            coordinates: (0, 0),
+            size: (usize::MAX, usize::MAX),
+        };
+        assert_eq!(huge.overlap_area(&claim), 16);
+        assert_eq!(huge.overlap_area(&huge), usize::MAX);
+    }
+
+    fn error(column: usize, expected: Expected, found: &str) -> Result<Claim, ClaimError> {
+        Err(ClaimError {
+            column,
+            expected,
+            found: found.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_claim_errors() {
+        assert_eq!(claim("#12 @ 3,4 5x5"), error(11, Expected::Colon, "5x5"));
+        assert_eq!(claim("12 @ 3,4: 5x5"), error(1, Expected::Hash, "12 @ 3,4: 5x5"));
+        assert_eq!(claim("#a @ 3,4: 5x5"), error(2, Expected::Number, "a @ 3,4: 5x5"));
+        assert_eq!(claim("#1 3,4: 5x5"), error(4, Expected::At, "3,4: 5x5"));
+        assert_eq!(claim("#1 @ 3;4: 5x5"), error(7, Expected::Comma, ";4: 5x5"));
+        assert_eq!(claim("#1 @ 3,4: 5*5"), error(12, Expected::Times, "*5"));
+        assert_eq!(claim("#1 @ 3,4: 5x5 6"), error(15, Expected::EndOfLine, "6"));
+        assert_eq!(claim("#1 @ 3,4: 5x"), error(13, Expected::Number, ""));
+        assert_eq!(claim("#é @ 3,4: 5x5"), error(2, Expected::Number, "é @ 3,4: 5x5"));
+        let huge = format!("#1 @ 3,{}: 5x5", usize::MAX - 4);
+        assert_eq!(claim(&huge), error(huge.len() - 2, Expected::SizeInRange, "5x5"));
+        let largest = format!("#1 @ 3,{}: 5x4", usize::MAX - 4);
+        assert_eq!(claim(&largest).unwrap().far_corner(), (8, usize::MAX));
+        assert_eq!(
+            claim("#99999999999999999999999 @ 3,4: 5x5"),
+            error(2, Expected::Number, "99999999999999999999999 @ 3,4: 5x5")
+        );
     }
 }
@@ -0,0 +1,364 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::parse::Claim;
+
+/// Two claims that overlap, and the area they share.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClaimOverlap {
+    pub first_id: usize,
+    pub second_id: usize,
+    pub area: usize,
+}
+
+/// Largest number of buckets a claim is stored in. Larger claims are kept apart, and checked by
+/// every query.
+const MAX_BUCKETS_PER_CLAIM: usize = 64;
+
+/// Range of the buckets of the given size covered by the interval of `length` from `start`
+/// along one axis (which must not be empty).
+fn bucket_range(start: usize, length: usize, bucket_size: usize) -> (usize, usize) {
+    (
+        start / bucket_size,
+        start.saturating_add(length - 1) / bucket_size,
+    )
+}
+
+/// Set of claims that can be queried for the claims at a position, and for the overlaps between
+/// claims.
+///
+/// The fabric is split into square buckets, about the size of a typical claim, and each claim is
+/// stored in the buckets it covers: a query only looks at the claims of the buckets it covers.
+/// Claims that would cover too many buckets are checked by every query instead.
+#[derive(Debug)]
+pub struct ClaimIndex {
+    claims: Vec<Claim>,
+    /// Side of the buckets.
+    bucket_size: usize,
+    /// Indices of the claims in each bucket that contains some, by bucket coordinates.
+    buckets: HashMap<(usize, usize), Vec<usize>>,
+    /// Indices of the claims too large to be stored in the buckets.
+    large: Vec<usize>,
+    /// Index of the first claim with each ID.
+    by_id: HashMap<usize, usize>,
+}
+
+impl ClaimIndex {
+    /// Build the index from the claims.
+    pub fn new(claims: Vec<Claim>) -> Self {
+        // The median side of the claims, so that most claims only cover a few buckets.
+        let mut sides = claims
+            .iter()
+            .map(|c| c.size.0.max(c.size.1))
+            .collect::<Vec<_>>();
+        sides.sort_unstable();
+        let bucket_size = sides.get(sides.len() / 2).cloned().unwrap_or(1).max(1);
+        let mut buckets = HashMap::new();
+        let mut large = Vec::new();
+        let mut by_id = HashMap::new();
+        for (index, claim) in claims.iter().enumerate() {
+            by_id.entry(claim.id).or_insert(index);
+            // Claims with no area don't cover any square inch.
+            if claim.size.0 == 0 || claim.size.1 == 0 {
+                continue;
+            }
+            let (x_min, x_max) = bucket_range(claim.coordinates.0, claim.size.0, bucket_size);
+            let (y_min, y_max) = bucket_range(claim.coordinates.1, claim.size.1, bucket_size);
+            if (x_max - x_min + 1).saturating_mul(y_max - y_min + 1) > MAX_BUCKETS_PER_CLAIM {
+                large.push(index);
+                continue;
+            }
+            for x in x_min..=x_max {
+                for y in y_min..=y_max {
+                    buckets.entry((x, y)).or_insert_with(Vec::new).push(index);
+                }
+            }
+        }
+        Self {
+            claims,
+            bucket_size,
+            buckets,
+            large,
+            by_id,
+        }
+    }
+
+    /// The claims in the index.
+    pub fn claims(&self) -> &[Claim] {
+        &self.claims
+    }
+
+    /// Sorted indices of the claims that may share some area with the rectangle at
+    /// `coordinates` of the given `size`.
+    fn candidates(&self, coordinates: (usize, usize), size: (usize, usize)) -> Vec<usize> {
+        if size.0 == 0 || size.1 == 0 {
+            return vec![];
+        }
+        let (x_min, x_max) = bucket_range(coordinates.0, size.0, self.bucket_size);
+        let (y_min, y_max) = bucket_range(coordinates.1, size.1, self.bucket_size);
+        let mut candidates = self.large.clone();
+        let covered = (x_max - x_min + 1).saturating_mul(y_max - y_min + 1);
+        if covered <= self.buckets.len() {
+            for x in x_min..=x_max {
+                for y in y_min..=y_max {
+                    if let Some(bucket) = self.buckets.get(&(x, y)) {
+                        candidates.extend(bucket);
+                    }
+                }
+            }
+        } else {
+            // The rectangle covers more buckets than there are, go through them instead.
+            for ((x, y), bucket) in &self.buckets {
+                if (x_min..=x_max).contains(x) && (y_min..=y_max).contains(y) {
+                    candidates.extend(bucket);
+                }
+            }
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+
+    /// IDs of the claims covering the square inch at `point`.
+    pub fn claims_at(&self, point: (usize, usize)) -> Vec<usize> {
+        self.candidates(point, (1, 1))
+            .into_iter()
+            .map(|i| &self.claims[i])
+            .filter(|c| c.contains(point))
+            .map(|c| c.id)
+            .collect()
+    }
+
+    /// IDs of the claims sharing some area with the rectangle at `coordinates` of the given
+    /// `size`.
+    pub fn claims_in(&self, coordinates: (usize, usize), size: (usize, usize)) -> Vec<usize> {
+        self.candidates(coordinates, size)
+            .into_iter()
+            .map(|i| &self.claims[i])
+            .filter(|c| c.intersection_area(coordinates, size) > 0)
+            .map(|c| c.id)
+            .collect()
+    }
+
+    /// List all the pairs of overlapping claims with their shared area, ordered by ID.
+    ///
+    /// Each claim is only compared with the following claims found in its buckets.
+    pub fn overlaps(&self) -> Vec<ClaimOverlap> {
+        let mut result = Vec::new();
+        for (i, claim) in self.claims.iter().enumerate() {
+            for j in self.candidates(claim.coordinates, claim.size) {
+                if j <= i {
+                    continue;
+                }
+                let other = &self.claims[j];
+                let area = claim.overlap_area(other);
+                if area > 0 {
+                    result.push(ClaimOverlap {
+                        first_id: claim.id.min(other.id),
+                        second_id: claim.id.max(other.id),
+                        area,
+                    });
+                }
+            }
+        }
+        result.sort_by_key(|o| (o.first_id, o.second_id));
+        result
+    }
+
+    /// Overlap areas of the claim with the given ID with every claim it overlaps, by ID.
+    pub fn overlaps_of(&self, id: usize) -> BTreeMap<usize, usize> {
+        let claim = match self.by_id.get(&id) {
+            Some(index) => &self.claims[*index],
+            None => return BTreeMap::new(),
+        };
+        self.candidates(claim.coordinates, claim.size)
+            .into_iter()
+            .map(|i| &self.claims[i])
+            .filter(|c| c.id != id)
+            .map(|c| (c.id, claim.overlap_area(c)))
+            .filter(|(_, area)| *area > 0)
+            .collect()
+    }
+
+    /// Graph of the overlaps: for each claim ID, the IDs of the claims it overlaps.
+    pub fn overlap_graph(&self) -> BTreeMap<usize, BTreeSet<usize>> {
+        let mut graph = self
+            .claims
+            .iter()
+            .map(|c| (c.id, BTreeSet::new()))
+            .collect::<BTreeMap<_, _>>();
+        for overlap in self.overlaps() {
+            graph
+                .get_mut(&overlap.first_id)
+                .unwrap()
+                .insert(overlap.second_id);
+            graph
+                .get_mut(&overlap.second_id)
+                .unwrap()
+                .insert(overlap.first_id);
+        }
+        graph
+    }
+
+    /// Connected components of the overlap graph, as sorted lists of IDs, ordered by their
+    /// smallest ID. Claims that don't overlap any other are alone in their component.
+    pub fn connected_components(&self) -> Vec<Vec<usize>> {
+        let graph = self.overlap_graph();
+        let mut seen = BTreeSet::new();
+        let mut components = Vec::new();
+        for id in graph.keys() {
+            if !seen.insert(*id) {
+                continue;
+            }
+            // Depth-first exploration from this claim.
+            let mut component = vec![];
+            let mut stack = vec![*id];
+            while let Some(current) = stack.pop() {
+                component.push(current);
+                for neighbour in &graph[&current] {
+                    if seen.insert(*neighbour) {
+                        stack.push(*neighbour);
+                    }
+                }
+            }
+            component.sort_unstable();
+            components.push(component);
+        }
+        components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_index() -> ClaimIndex {
+        ClaimIndex::new(vec![
+            Claim {
+                id: 1,
+                coordinates: (1, 3),
+                size: (4, 4),
+            },
+            Claim {
+                id: 2,
+                coordinates: (3, 1),
+                size: (4, 4),
+            },
+            Claim {
+                id: 3,
+                coordinates: (5, 5),
+                size: (2, 2),
+            },
+            Claim {
+                id: 4,
+                coordinates: (4, 0),
+                size: (1, 2),
+            },
+        ])
+    }
+
+    #[test]
+    fn test_claims_at() {
+        let index = example_index();
+        assert_eq!(vec![1, 2], index.claims_at((3, 3)));
+        assert_eq!(vec![3], index.claims_at((5, 5)));
+        assert_eq!(Vec::<usize>::new(), index.claims_at((7, 7)));
+        assert_eq!(vec![2, 4], index.claims_at((4, 1)));
+    }
+
+    #[test]
+    fn test_claims_in() {
+        let index = example_index();
+        assert_eq!(vec![1, 2, 3], index.claims_in((4, 4), (2, 2)));
+        assert_eq!(vec![4], index.claims_in((0, 0), (5, 1)));
+        assert_eq!(Vec::<usize>::new(), index.claims_in((0, 0), (1, 3)));
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let index = example_index();
+        assert_eq!(
+            vec![
+                ClaimOverlap {
+                    first_id: 1,
+                    second_id: 2,
+                    area: 4,
+                },
+                ClaimOverlap {
+                    first_id: 2,
+                    second_id: 4,
+                    area: 1,
+                },
+            ],
+            index.overlaps()
+        );
+        let expected: BTreeMap<usize, usize> = [(1, 4), (4, 1)].iter().cloned().collect();
+        assert_eq!(expected, index.overlaps_of(2));
+        assert!(index.overlaps_of(3).is_empty());
+        assert!(index.overlaps_of(42).is_empty());
+    }
+
+    #[test]
+    fn test_connected_components() {
+        let index = example_index();
+        assert_eq!(vec![vec![1, 2, 4], vec![3]], index.connected_components());
+        let graph = index.overlap_graph();
+        assert_eq!(vec![1, 4], graph[&2].iter().cloned().collect::<Vec<_>>());
+        assert!(graph[&3].is_empty());
+    }
+
+    #[test]
+    fn test_buckets_match_scan() {
+        // Pseudo-random claims, a few of them much larger than the others.
+        let mut seed: usize = 7;
+        let mut next = |modulo: usize| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 33) % modulo
+        };
+        let claims = (1..=80)
+            .map(|id| {
+                let side = if id % 20 == 0 { 60 } else { 8 };
+                Claim {
+                    id,
+                    coordinates: (next(100), next(100)),
+                    size: (next(side), next(side)),
+                }
+            })
+            .collect::<Vec<_>>();
+        let index = ClaimIndex::new(claims.clone());
+        assert!(!index.large.is_empty());
+        for x in (0..110).step_by(7) {
+            for y in (0..110).step_by(5) {
+                let at = claims
+                    .iter()
+                    .filter(|c| c.contains((x, y)))
+                    .map(|c| c.id)
+                    .collect::<Vec<_>>();
+                assert_eq!(at, index.claims_at((x, y)));
+                let size = (x % 13, y % 17 + 1);
+                let inside = claims
+                    .iter()
+                    .filter(|c| c.intersection_area((x, y), size) > 0)
+                    .map(|c| c.id)
+                    .collect::<Vec<_>>();
+                assert_eq!(inside, index.claims_in((x, y), size));
+            }
+        }
+        let mut overlaps = vec![];
+        for (i, claim) in claims.iter().enumerate() {
+            for other in &claims[i + 1..] {
+                let area = claim.overlap_area(other);
+                if area > 0 {
+                    overlaps.push((claim.id, other.id, area));
+                }
+            }
+        }
+        assert_eq!(
+            overlaps,
+            index
+                .overlaps()
+                .iter()
+                .map(|o| (o.first_id, o.second_id, o.area))
+                .collect::<Vec<_>>()
+        );
+    }
+}
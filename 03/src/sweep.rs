@@ -0,0 +1,341 @@
+use crate::parse::Claim;
+
+/// Segment tree over the elementary intervals between sorted coordinates, which keeps track of
+/// the length covered at least once and at least twice by the inserted intervals.
+struct CoverageTree {
+    /// Sorted, deduplicated coordinates: leaf i is the interval [coords[i], coords[i + 1]).
+    coords: Vec<usize>,
+    /// Number of intervals covering the whole node (but not its parent).
+    count: Vec<usize>,
+    /// Length covered at least once within the node.
+    once: Vec<usize>,
+    /// Length covered at least twice within the node.
+    twice: Vec<usize>,
+}
+
+impl CoverageTree {
+    fn new(coords: Vec<usize>) -> Self {
+        let size = 4 * coords.len().max(1);
+        Self {
+            coords,
+            count: vec![0; size],
+            once: vec![0; size],
+            twice: vec![0; size],
+        }
+    }
+
+    /// Number of leaves (elementary intervals).
+    fn leaves(&self) -> usize {
+        self.coords.len().saturating_sub(1)
+    }
+
+    /// Length covered at least twice.
+    fn covered_twice(&self) -> usize {
+        self.twice[1]
+    }
+
+    /// Add `delta` (1 or -1) to the coverage of [start, end).
+    fn update(&mut self, start: usize, end: usize, delta: isize) {
+        let start = self.coords.binary_search(&start).unwrap();
+        let end = self.coords.binary_search(&end).unwrap();
+        if start < end {
+            self.update_node(1, 0, self.leaves(), start, end, delta);
+        }
+    }
+
+    fn update_node(
+        &mut self,
+        node: usize,
+        low: usize,
+        high: usize,
+        start: usize,
+        end: usize,
+        delta: isize,
+    ) {
+        if end <= low || high <= start {
+            return;
+        }
+        if start <= low && high <= end {
+            self.count[node] = (self.count[node] as isize + delta) as usize;
+        } else {
+            let middle = (low + high) / 2;
+            self.update_node(2 * node, low, middle, start, end, delta);
+            self.update_node(2 * node + 1, middle, high, start, end, delta);
+        }
+        self.pull(node, low, high);
+    }
+
+    /// Recompute the covered lengths of a node from its count and its children.
+    fn pull(&mut self, node: usize, low: usize, high: usize) {
+        let full = self.coords[high] - self.coords[low];
+        let is_leaf = high - low == 1;
+        let (children_once, children_twice) = if is_leaf {
+            (0, 0)
+        } else {
+            (
+                self.once[2 * node] + self.once[2 * node + 1],
+                self.twice[2 * node] + self.twice[2 * node + 1],
+            )
+        };
+        self.once[node] = if self.count[node] > 0 {
+            full
+        } else {
+            children_once
+        };
+        self.twice[node] = match self.count[node] {
+            0 => children_twice,
+            1 => children_once,
+            _ => full,
+        };
+    }
+}
+
+/// Segment tree indexed by the rank of the start of intervals, storing the furthest end of the
+/// intervals in each node. It answers "is there an interval intersecting [start, end)?".
+struct IntervalTree {
+    size: usize,
+    /// Furthest end and index of the corresponding interval, for each node.
+    max_end: Vec<Option<(usize, usize)>>,
+}
+
+impl IntervalTree {
+    fn new(size: usize) -> Self {
+        Self {
+            size,
+            max_end: vec![None; 4 * size.max(1)],
+        }
+    }
+
+    /// Set (or clear, with None) the interval with the given rank.
+    fn set(&mut self, rank: usize, value: Option<(usize, usize)>) {
+        self.set_node(1, 0, self.size, rank, value);
+    }
+
+    fn set_node(
+        &mut self,
+        node: usize,
+        low: usize,
+        high: usize,
+        rank: usize,
+        value: Option<(usize, usize)>,
+    ) {
+        if high - low == 1 {
+            self.max_end[node] = value;
+            return;
+        }
+        let middle = (low + high) / 2;
+        if rank < middle {
+            self.set_node(2 * node, low, middle, rank, value);
+        } else {
+            self.set_node(2 * node + 1, middle, high, rank, value);
+        }
+        self.max_end[node] = self.max_end[2 * node].max(self.max_end[2 * node + 1]);
+    }
+
+    /// Find an interval among the ranks below `limit` (i.e. starting before the end of the query)
+    /// that ends after `start`. Returns its index.
+    fn find(&self, limit: usize, start: usize) -> Option<usize> {
+        self.find_node(1, 0, self.size, limit, start)
+    }
+
+    fn find_node(
+        &self,
+        node: usize,
+        low: usize,
+        high: usize,
+        limit: usize,
+        start: usize,
+    ) -> Option<usize> {
+        if low >= limit {
+            return None;
+        }
+        match self.max_end[node] {
+            Some((end, index)) if end > start => {
+                if high <= limit {
+                    // The whole node is in range, return any matching interval. Going down
+                    // towards the maximum is only needed to find its index, which we store.
+                    return Some(index);
+                }
+            }
+            _ => return None,
+        }
+        let middle = (low + high) / 2;
+        self.find_node(2 * node, low, middle, limit, start)
+            .or_else(|| self.find_node(2 * node + 1, middle, high, limit, start))
+    }
+}
+
+/// Result of the sweep: the area covered by at least 2 claims, and the IDs of the claims that
+/// don't overlap any other claim, sorted.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Overlaps {
+    pub area: usize,
+    pub isolated_ids: Vec<usize>,
+}
+
+/// Compute the overlapping area and the isolated claims with a sweep line along the x axis. The
+/// complexity only depends on the number of claims, not on their coordinates or sizes.
+///
+/// Every claim start and end is an event. Between two consecutive events, the area covered twice
+/// is the length covered twice along the y axis (maintained by a `CoverageTree`) times the width
+/// of the slab. To find the overlapping claims, we keep the claims currently crossed by the sweep
+/// line in two `IntervalTree`s over their y intervals: one with all of them, to check whether a new
+/// claim overlaps any of them, and one with the claims not yet known to overlap, from which we
+/// remove every claim that a new claim overlaps. Each claim is removed at most once.
+pub fn sweep_overlaps(claims: &[Claim]) -> Overlaps {
+    // Claims with no area can't overlap anything, we leave them out of the sweep.
+    let has_area = |c: &Claim| c.size.0 > 0 && c.size.1 > 0;
+    // Compressed y coordinates for the coverage.
+    let mut y_coords = claims
+        .iter()
+        .filter(|c| has_area(c))
+        .flat_map(|c| vec![c.coordinates.1, c.far_corner().1])
+        .collect::<Vec<_>>();
+    y_coords.sort_unstable();
+    y_coords.dedup();
+    let mut coverage = CoverageTree::new(y_coords);
+    // Rank of each claim when sorted by the start of its y interval.
+    let mut by_y_start = (0..claims.len()).collect::<Vec<_>>();
+    by_y_start.sort_unstable_by_key(|&i| claims[i].coordinates.1);
+    let mut rank = vec![0; claims.len()];
+    by_y_start
+        .iter()
+        .enumerate()
+        .for_each(|(r, &i)| rank[i] = r);
+    let y_starts = by_y_start
+        .iter()
+        .map(|&i| claims[i].coordinates.1)
+        .collect::<Vec<_>>();
+    let mut active = IntervalTree::new(claims.len());
+    let mut unmarked = IntervalTree::new(claims.len());
+    let mut overlapping = vec![false; claims.len()];
+    // Events: (x, is_start, claim index). Ends come before starts at the same x, since the
+    // claims are half-open intervals.
+    let mut events = claims
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| has_area(c))
+        .flat_map(|(i, c)| {
+            vec![
+                (c.coordinates.0, true, i),
+                (c.far_corner().0, false, i),
+            ]
+        })
+        .collect::<Vec<_>>();
+    events.sort_unstable();
+    let mut area = 0;
+    let mut last_x = events.first().map_or(0, |e| e.0);
+    for (x, is_start, index) in events {
+        area += coverage.covered_twice() * (x - last_x);
+        last_x = x;
+        let claim = &claims[index];
+        let (start, end) = (claim.coordinates.1, claim.far_corner().1);
+        if is_start {
+            coverage.update(start, end, 1);
+            // Claims starting (in y) before the end of this one.
+            let limit = y_starts.partition_point(|&y| y < end);
+            if active.find(limit, start).is_some() {
+                overlapping[index] = true;
+            }
+            while let Some(other) = unmarked.find(limit, start) {
+                overlapping[other] = true;
+                overlapping[index] = true;
+                unmarked.set(rank[other], None);
+            }
+            active.set(rank[index], Some((end, index)));
+            if !overlapping[index] {
+                unmarked.set(rank[index], Some((end, index)));
+            }
+        } else {
+            coverage.update(start, end, -1);
+            active.set(rank[index], None);
+            unmarked.set(rank[index], None);
+        }
+    }
+    let mut isolated_ids = claims
+        .iter()
+        .zip(overlapping)
+        .filter(|(_, o)| !o)
+        .map(|(c, _)| c.id)
+        .collect::<Vec<_>>();
+    isolated_ids.sort_unstable();
+    Overlaps { area, isolated_ids }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claim(id: usize, coordinates: (usize, usize), size: (usize, usize)) -> Claim {
+        Claim {
+            id,
+            coordinates,
+            size,
+        }
+    }
+
+    #[test]
+    fn test_example() {
+        let claims = vec![
+            claim(1, (1, 3), (4, 4)),
+            claim(2, (3, 1), (4, 4)),
+            claim(3, (5, 5), (2, 2)),
+        ];
+        assert_eq!(
+            Overlaps {
+                area: 4,
+                isolated_ids: vec![3],
+            },
+            sweep_overlaps(&claims)
+        );
+    }
+
+    #[test]
+    fn test_touching_and_nested() {
+        let claims = vec![
+            // Touches the next one without overlapping.
+            claim(1, (0, 0), (2, 2)),
+            claim(2, (2, 0), (2, 2)),
+            // Contains the next one: 3 layers over 1x1.
+            claim(3, (10, 10), (5, 5)),
+            claim(4, (11, 11), (3, 3)),
+            claim(5, (12, 12), (1, 1)),
+            // Empty claim.
+            claim(6, (11, 11), (0, 4)),
+        ];
+        assert_eq!(
+            Overlaps {
+                area: 9,
+                isolated_ids: vec![1, 2, 6],
+            },
+            sweep_overlaps(&claims)
+        );
+    }
+
+    #[test]
+    fn test_huge_coordinates() {
+        let claims = vec![
+            claim(1, (1_000_000_000, 2_000_000_000), (3_000_000, 1_000_000)),
+            claim(2, (1_002_000_000, 2_000_500_000), (3_000_000, 1_000_000)),
+            claim(3, (5, 5), (1, 1)),
+        ];
+        assert_eq!(
+            Overlaps {
+                area: 1_000_000 * 500_000,
+                isolated_ids: vec![3],
+            },
+            sweep_overlaps(&claims)
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(
+            Overlaps {
+                area: 0,
+                isolated_ids: vec![],
+            },
+            sweep_overlaps(&[])
+        );
+    }
+}
@@ -13,6 +13,7 @@ pub struct Vec2D<T> {
 impl<T> Index<(usize, usize)> for Vec2D<T> {
     type Output = T;
     fn index(&self, i: (usize, usize)) -> &T {
+        debug_assert!(i.0 < self.height && i.1 < self.width);
         &self.vec[i.0 * self.width + i.1]
     }
 }
@@ -21,6 +22,7 @@ impl<T> Index<(usize, usize)> for Vec2D<T> {
 impl<T> IndexMut<(usize, usize)> for Vec2D<T> {
     //type Output = T;
     fn index_mut(&mut self, i: (usize, usize)) -> &mut T {
+        debug_assert!(i.0 < self.height && i.1 < self.width);
         &mut self.vec[i.0 * self.width + i.1]
     }
 }
@@ -28,10 +30,34 @@ impl<T> IndexMut<(usize, usize)> for Vec2D<T> {
 /// Build a 2-D vector by filling it with the values returned by the generator
 /// (it gets called for each cell).
 impl<T> Vec2D<T> {
-    pub fn from_fn(height: usize, width: usize, generator: &Fn() -> T) -> Self {
-        let vec = (0..(height * width))
+    pub fn from_fn(height: usize, width: usize, generator: &dyn Fn() -> T) -> Self {
+        let cells = height
+            .checked_mul(width)
+            .expect("The 2-D vector is too large");
+        let vec = (0..cells)
             .map(|_| generator())
             .collect::<Vec<_>>();
         Self { height, width, vec }
     }
+
+    /// Number of rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of columns.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Change the size of the 2-D vector, keeping the values that are still in range and filling
+    /// the new cells with the values returned by the generator.
+    pub fn resize(&mut self, height: usize, width: usize, generator: &dyn Fn() -> T) {
+        let mut old = std::mem::replace(self, Self::from_fn(height, width, generator));
+        for i in 0..old.height.min(height) {
+            for j in 0..old.width.min(width) {
+                std::mem::swap(&mut self[(i, j)], &mut old[(i, j)]);
+            }
+        }
+    }
 }
@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::slice;
+
+use crate::histogram::{build_sleep_histogram, Bucket};
+use crate::parse::chrono::NaiveDate;
+use crate::parse::GuardId;
+use crate::DayEvent;
+
+/// Number of minutes shown in the charts: the midnight hour, 00:00 to 00:59.
+const MINUTES: usize = 60;
+
+/// Selection of the days to show in the charts. The default shows every day.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChartFilter {
+    /// Only show the shifts of this guard.
+    pub guard: Option<GuardId>,
+    /// Only show the days from this date, included.
+    pub from: Option<NaiveDate>,
+    /// Only show the days up to this date, included.
+    pub to: Option<NaiveDate>,
+}
+
+impl ChartFilter {
+    /// Return whether the day should be shown.
+    fn matches(&self, event: &DayEvent) -> bool {
+        self.guard.iter().all(|g| *g == event.guard)
+            && self.from.iter().all(|d| *d <= event.date)
+            && self.to.iter().all(|d| event.date <= *d)
+    }
+}
+
+/// Width of the ID column: the longest "#id", or the header.
+fn id_width<'a, I: Iterator<Item = &'a GuardId>>(guards: I) -> usize {
+    guards
+        .map(|g| format!("#{}", u32::from(*g)).len())
+        .max()
+        .unwrap_or(0)
+        .max("ID".len())
+}
+
+/// The two header lines with the tens and the ones of each minute, after `indent` spaces.
+fn minute_header(indent: usize) -> String {
+    let tens: String = (0..MINUTES).map(|m| (b'0' + (m / 10) as u8) as char).collect();
+    let ones: String = (0..MINUTES).map(|m| (b'0' + (m % 10) as u8) as char).collect();
+    format!("{:indent$}{}\n{:indent$}{}\n", "", tens, "", ones, indent = indent)
+}
+
+/// Render the days as in the puzzle: one line per day with the date, the guard, and a `#` for
+/// every minute of the midnight hour during which the guard was asleep, `.` otherwise.
+///
+/// The days must be sorted, as returned by `summarize_days`. Naps outside of the midnight hour
+/// are not shown.
+///
+/// For example:
+/// ```
+/// let lines = vec![
+///     "[1518-11-01 00:00] Guard #10 begins shift".to_string(),
+///     "[1518-11-01 00:05] falls asleep".to_string(),
+///     "[1518-11-01 00:25] wakes up".to_string(),
+///     "[1518-11-01 23:58] Guard #99 begins shift".to_string(),
+///     "[1518-11-02 00:40] falls asleep".to_string(),
+///     "[1518-11-02 00:50] wakes up".to_string(),
+/// ];
+/// let days = lib::summarize_days(&lines);
+/// let chart = lib::render_chart(&days, &lib::ChartFilter::default());
+/// assert_eq!(
+///     chart.lines().collect::<Vec<_>>(),
+///     vec![
+///         "Date   ID   Minute",
+///         "            000000000011111111112222222222333333333344444444445555555555",
+///         "            012345678901234567890123456789012345678901234567890123456789",
+///         "11-01  #10  .....####################...................................",
+///         "11-02  #99  ........................................##########..........",
+///     ]
+/// );
+/// ```
+pub fn render_chart(days: &[DayEvent], filter: &ChartFilter) -> String {
+    let days = days.iter().filter(|d| filter.matches(d)).collect::<Vec<_>>();
+    let width = id_width(days.iter().map(|d| &d.guard));
+    let mut chart = format!("Date   {:width$}  Minute\n", "ID", width = width);
+    chart.push_str(&minute_header(7 + width + 2));
+    for day in days {
+        let histogram = build_sleep_histogram(slice::from_ref(day), Bucket::MinuteOfDay);
+        let minutes: String = histogram[..MINUTES]
+            .iter()
+            .map(|&count| if count > 0 { '#' } else { '.' })
+            .collect();
+        let id = format!("#{}", u32::from(day.guard));
+        writeln!(
+            chart,
+            "{}  {:width$}  {}",
+            day.date.format("%m-%d"),
+            id,
+            minutes,
+            width = width
+        )
+        .unwrap();
+    }
+    chart
+}
+
+/// Render one heat row per guard, sorted by ID: for every minute of the midnight hour, the
+/// number of days the guard was asleep, from `1` to `9` then from `a` (10) to `z` (35), `+` for
+/// more and `.` for none.
+///
+/// Only the days selected by the filter are counted.
+pub fn render_heat_rows(days: &[DayEvent], filter: &ChartFilter) -> String {
+    let mut days_by_guard: BTreeMap<u32, Vec<&DayEvent>> = BTreeMap::new();
+    for day in days.iter().filter(|d| filter.matches(d)) {
+        days_by_guard
+            .entry(u32::from(day.guard))
+            .or_default()
+            .push(day);
+    }
+    let width = id_width(days_by_guard.values().map(|days| &days[0].guard));
+    let mut chart = format!("{:width$}  Minute\n", "ID", width = width);
+    chart.push_str(&minute_header(width + 2));
+    for (guard, days) in days_by_guard {
+        let mut histogram = vec![0; MINUTES];
+        for day in days {
+            let day_histogram = build_sleep_histogram(slice::from_ref(day), Bucket::MinuteOfDay);
+            for (count, day_count) in histogram.iter_mut().zip(&day_histogram[..MINUTES]) {
+                *count += day_count;
+            }
+        }
+        let minutes: String = histogram
+            .iter()
+            .map(|&count| match count {
+                0 => '.',
+                1..=35 => std::char::from_digit(count, 36).unwrap(),
+                _ => '+',
+            })
+            .collect();
+        let id = format!("#{}", guard);
+        writeln!(chart, "{:width$}  {}", id, minutes, width = width).unwrap();
+    }
+    chart
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Example log from the subject, unsorted.
+    const EXAMPLE: &str = include_str!("../input_example");
+
+    fn example_days() -> Vec<DayEvent> {
+        let lines = EXAMPLE.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+        crate::summarize_days(&lines)
+    }
+
+    fn date(s: &str) -> Option<NaiveDate> {
+        Some(NaiveDate::parse_from_str(s, "%F").unwrap())
+    }
+
+    #[test]
+    fn test_render_chart() {
+        let expected = "\
+Date   ID   Minute
+            000000000011111111112222222222333333333344444444445555555555
+            012345678901234567890123456789012345678901234567890123456789
+11-01  #10  .....####################.....#########################.....
+11-02  #99  ........................................##########..........
+11-03  #10  ........................#####...............................
+11-04  #99  ....................................##########..............
+11-05  #99  .............................................##########.....
+";
+        assert_eq!(expected, render_chart(&example_days(), &ChartFilter::default()));
+    }
+
+    #[test]
+    fn test_render_chart_filtered() {
+        let filter = ChartFilter {
+            guard: Some(GuardId::from(99)),
+            from: date("1518-11-03"),
+            to: None,
+        };
+        let chart = render_chart(&example_days(), &filter);
+        let rows = chart.lines().skip(3).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "11-04  #99  ....................................##########..............",
+                "11-05  #99  .............................................##########.....",
+            ],
+            rows
+        );
+        let filter = ChartFilter {
+            to: date("1518-11-01"),
+            ..ChartFilter::default()
+        };
+        assert_eq!(4, render_chart(&example_days(), &filter).lines().count());
+    }
+
+    #[test]
+    fn test_render_heat_rows() {
+        let expected = "\
+ID   Minute
+     000000000011111111112222222222333333333344444444445555555555
+     012345678901234567890123456789012345678901234567890123456789
+#10  .....111111111111111111121111.1111111111111111111111111.....
+#99  ....................................1111222223222211111.....
+";
+        assert_eq!(expected, render_heat_rows(&example_days(), &ChartFilter::default()));
+    }
+
+    #[test]
+    fn test_heat_digits() {
+        let days = (1..=12)
+            .map(|d| {
+                let date = NaiveDate::from_ymd_opt(1518, 3, d).unwrap();
+                DayEvent {
+                    guard: GuardId::from(1234),
+                    date,
+                    sleep_times: vec![(
+                        date.and_hms_opt(0, 0, 0).unwrap(),
+                        date.and_hms_opt(0, d / 2, 0).unwrap(),
+                    )],
+                }
+            })
+            .collect::<Vec<_>>();
+        let chart = render_heat_rows(&days, &ChartFilter::default());
+        assert_eq!(
+            Some("#1234  b97531......................................................"),
+            chart.lines().nth(3)
+        );
+        assert!(chart.starts_with("ID     Minute\n"));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::parse::chrono::{Datelike, Duration, NaiveDateTime, Timelike};
+use crate::DayEvent;
+
+/// Granularity of the sleep histograms: each minute asleep is counted in the bucket of its time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bucket {
+    /// Minute within the hour, 0 to 59.
+    MinuteOfHour,
+    /// Minute within the day, 0 (00:00) to 1439 (23:59).
+    MinuteOfDay,
+    /// Hour of the day, 0 to 23.
+    HourOfDay,
+    /// Day of the week, 0 (Monday) to 6 (Sunday).
+    Weekday,
+}
+
+impl Bucket {
+    /// Number of buckets.
+    pub fn count(self) -> usize {
+        match self {
+            Bucket::MinuteOfHour => 60,
+            Bucket::MinuteOfDay => 24 * 60,
+            Bucket::HourOfDay => 24,
+            Bucket::Weekday => 7,
+        }
+    }
+
+    /// Index of the bucket that `time` falls in.
+    pub fn index(self, time: NaiveDateTime) -> usize {
+        let index = match self {
+            Bucket::MinuteOfHour => time.minute(),
+            Bucket::MinuteOfDay => time.hour() * 60 + time.minute(),
+            Bucket::HourOfDay => time.hour(),
+            Bucket::Weekday => time.weekday().num_days_from_monday(),
+        };
+        index as usize
+    }
+}
+
+/// Count, for each bucket, the number of minutes the guard was asleep during the given shifts.
+///
+/// With minute buckets, this is the number of days the guard was asleep at that minute.
+pub fn build_sleep_histogram(events: &[DayEvent], bucket: Bucket) -> Vec<u32> {
+    let mut histogram = vec![0; bucket.count()];
+    for event in events {
+        for (nap_start, nap_end) in &event.sleep_times {
+            let mut time = *nap_start;
+            while time < *nap_end {
+                histogram[bucket.index(time)] += 1;
+                time += Duration::minutes(1);
+            }
+        }
+    }
+    histogram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GuardId;
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%F %R").unwrap()
+    }
+
+    fn day_event(naps: &[(&str, &str)]) -> DayEvent {
+        DayEvent {
+            guard: GuardId::from(10),
+            sleep_times: naps.iter().map(|(s, e)| (time(s), time(e))).collect(),
+            ..DayEvent::default()
+        }
+    }
+
+    #[test]
+    fn test_bucket_index() {
+        let t = time("1518-11-01 23:58");
+        assert_eq!(58, Bucket::MinuteOfHour.index(t));
+        assert_eq!(1438, Bucket::MinuteOfDay.index(t));
+        assert_eq!(23, Bucket::HourOfDay.index(t));
+        // 1518-11-01 is a Friday.
+        assert_eq!(4, Bucket::Weekday.index(t));
+    }
+
+    #[test]
+    fn test_nap_across_midnight() {
+        let events = vec![day_event(&[("1518-11-01 23:58", "1518-11-02 00:05")])];
+        let histogram = build_sleep_histogram(&events, Bucket::MinuteOfDay);
+        assert_eq!(7, histogram.iter().sum::<u32>());
+        assert_eq!(1, histogram[1438]);
+        assert_eq!(1, histogram[1439]);
+        assert_eq!(1, histogram[4]);
+        assert_eq!(0, histogram[5]);
+        let histogram = build_sleep_histogram(&events, Bucket::HourOfDay);
+        assert_eq!(2, histogram[23]);
+        assert_eq!(5, histogram[0]);
+        let histogram = build_sleep_histogram(&events, Bucket::Weekday);
+        assert_eq!(vec![0, 0, 0, 0, 2, 5, 0], histogram);
+    }
+
+    #[test]
+    fn test_minute_of_hour() {
+        let events = vec![
+            day_event(&[("1518-11-01 00:05", "1518-11-01 00:07")]),
+            day_event(&[
+                ("1518-11-02 00:06", "1518-11-02 00:08"),
+                ("1518-11-02 01:05", "1518-11-02 01:06"),
+            ]),
+        ];
+        let histogram = build_sleep_histogram(&events, Bucket::MinuteOfHour);
+        assert_eq!(&[2, 2, 1], &histogram[5..8]);
+        assert_eq!(5, histogram.iter().sum::<u32>());
+    }
+}
@@ -4,20 +4,35 @@ extern crate nom;
 use std::string::String;
 use std::collections::HashMap;
 
+mod chart;
+mod histogram;
 mod parse;
+mod strategy;
+mod validate;
 
 /// Import the datatypes from the parser.
-use crate::parse::{DatedEvent, GuardEvent, GuardId};
-/// Import the trait so we can access .min() for `NaiveTime`.
-use crate::parse::chrono::Timelike;
+use crate::parse::{DatedEvent, GuardEvent};
+
+use crate::parse::chrono::{Duration, NaiveDate, NaiveDateTime, Timelike};
+
+pub use crate::chart::{render_chart, render_heat_rows, ChartFilter};
+pub use crate::histogram::{build_sleep_histogram, Bucket};
+pub use crate::parse::GuardId;
+pub use crate::strategy::{
+    LongestNap, MostConsistentBucket, Report, SleepiestBucket, SleepiestGuard, Strategy,
+};
+pub use crate::validate::{Anomaly, AnomalyKind};
 
 /// All the events of a given day.
 #[derive(Debug, Eq, PartialEq, Default)]
-struct DayEvent {
+pub struct DayEvent {
     /// Id of the guard.
-    guard: GuardId,
+    pub guard: GuardId,
+    /// Date of the midnight hour of the shift: shifts that begin in the
+    /// evening count for the next day.
+    pub date: NaiveDate,
     /// Times at which the guard fell asleep and woke up.
-    sleep_times: Vec<(u32, u32)>,
+    pub sleep_times: Vec<(NaiveDateTime, NaiveDateTime)>,
 }
 
 /// Parse each line from the logs, and sort them by time.
@@ -27,11 +42,23 @@ fn parse_and_sort_lines(lines: &[String]) -> Vec<DatedEvent> {
     result
 }
 
+/// Date of the midnight hour that follows or includes the beginning of a
+/// shift.
+fn shift_date(begin: NaiveDateTime) -> NaiveDate {
+    if begin.hour() >= 12 {
+        (begin + Duration::days(1)).date()
+    } else {
+        begin.date()
+    }
+}
+
 /// Summarize the events, grouping them by day (guard).
+///
+/// The events must have been validated with `validate::validate_events`.
 fn summarize_by_day(parsed_lines: Vec<DatedEvent>) -> Vec<DayEvent> {
     let mut day_events = Vec::new();
     let mut day_event = DayEvent::default();
-    let mut asleep_time: Option<u32> = None;
+    let mut asleep_time: Option<NaiveDateTime> = None;
     for event in parsed_lines {
         match event.event {
             GuardEvent::ShiftBegin(id) => {
@@ -42,28 +69,51 @@ fn summarize_by_day(parsed_lines: Vec<DatedEvent>) -> Vec<DayEvent> {
                 }
                 day_event = DayEvent::default();
                 day_event.guard = id;
+                day_event.date = shift_date(event.date);
             }
             //
             GuardEvent::FallsAsleep => {
                 // Record the time the guard fell asleep.
-                assert!(asleep_time == None);
-                asleep_time = Some(event.date.minute());
+                debug_assert!(asleep_time.is_none());
+                asleep_time = Some(event.date);
             }
             GuardEvent::WakesUp => {
                 // Record the time the guard woke up, with the time he fell
                 // asleep.
-                assert!(asleep_time != None);
+                debug_assert!(asleep_time.is_some());
                 day_event.sleep_times.push((
                     asleep_time.unwrap(),
-                    event.date.minute(),
+                    event.date,
                 ));
                 asleep_time = None;
             }
         }
     }
+    // Push the last day, if there was any.
+    if day_event.guard != GuardId::default() {
+        day_events.push(day_event);
+    }
     day_events
 }
 
+/// List the problems in the log: events out of a shift, unmatched sleep or
+/// wake events, duplicate times.
+///
+/// The input is the lines of the log, unsorted.
+pub fn find_anomalies(lines: &[String]) -> Vec<Anomaly> {
+    validate::validate_events(parse_and_sort_lines(lines)).1
+}
+
+/// Parse, validate and summarize the log, to get the shifts of every day,
+/// sorted by date.
+///
+/// The input is the lines of the log, unsorted. The anomalies of the log are
+/// skipped, see `find_anomalies`.
+pub fn summarize_days(lines: &[String]) -> Vec<DayEvent> {
+    let (parsed_lines, _) = validate::validate_events(parse_and_sort_lines(lines));
+    summarize_by_day(parsed_lines)
+}
+
 /// Summarize the events by guard.
 fn summarize_by_guard(events: Vec<DayEvent>) -> HashMap<GuardId, Vec<DayEvent>> {
     let mut events_by_guard = HashMap::new();
@@ -78,105 +128,144 @@ fn summarize_by_guard(events: Vec<DayEvent>) -> HashMap<GuardId, Vec<DayEvent>>
     events_by_guard
 }
 
-/// Sum the duration of the naps of a single day.
-fn time_slept_in_day(event: &DayEvent) -> u32 {
+/// Sum the duration of the naps of a single day, in minutes.
+fn time_slept_in_day(event: &DayEvent) -> i64 {
     event.sleep_times.iter().fold(0, |sum, (start, end)| {
-        sum + end - start
+        sum + (*end - *start).num_minutes()
     })
 }
 
-/// Sum the duration of all the given naps.
-fn time_slept(events: &[DayEvent]) -> u32 {
+/// Sum the duration of all the given naps, in minutes.
+pub(crate) fn time_slept(events: &[DayEvent]) -> i64 {
     events.iter().map(time_slept_in_day).sum()
 }
 
-/// Find the guard who slept the longest overall.
-fn find_sleepiest_guard(events_by_guard: &HashMap<GuardId, Vec<DayEvent>>) -> GuardId {
-    // The ID is from the table, we know it's there.
-    *events_by_guard
-        .iter()
-        .max_by_key(|(_id, events)| time_slept(events))
-        .unwrap()
-        .0
-}
-
-fn build_sleep_histogram(events: &[DayEvent]) -> [u32; 60] {
-    let mut histogram = [0; 60];
-    for event in events {
-        for (nap_start, nap_end) in event.sleep_times.clone() {
-            for i in nap_start..nap_end {
-                histogram[i as usize] += 1;
-            }
-        }
-    }
-    histogram
-}
-
-/// Given a single guard, find the minute where he was most often asleep.
-fn find_sleepiest_minute_for_guard(events: &[DayEvent]) -> (u32, u32) {
-    let histogram = build_sleep_histogram(events);
-    let (index, value) = histogram
-        .iter()
-        .enumerate()
-        // Max count per minute.
-        .max_by_key(|k| k.1)
-        .unwrap();
-    #[allow(clippy::cast_possible_truncation)]
-    (index as u32, *value)
+/// Parse, validate and summarize the log, to get the shifts of each guard.
+///
+/// The input is the lines of the log, unsorted. The anomalies of the log are
+/// skipped, see `find_anomalies`.
+pub fn summarize_log(lines: &[String]) -> HashMap<GuardId, Vec<DayEvent>> {
+    summarize_by_guard(summarize_days(lines))
 }
 
-/// Find the guard that is the most likely to be sleeping at a specific minute,
-/// along with the minute he is the most likely to be sleeping.
-fn find_sleepiest_guard_at_minute(
-    events_by_guard: &HashMap<GuardId, Vec<DayEvent>>,
-) -> (&GuardId, u32) {
-    let (guard, (minute, _count)) = events_by_guard
+/// Run each strategy on the log, with naps counted by `bucket`, and return
+/// the report of each strategy that found a guard.
+pub fn run_strategies(
+    lines: &[String],
+    strategies: &[&dyn Strategy],
+    bucket: Bucket,
+) -> Vec<Report> {
+    let guard_to_events = summarize_log(lines);
+    strategies
         .iter()
-        // For each guard find the minute they are most likely to be sleeping.
-        .map(|(g, events)| (g, find_sleepiest_minute_for_guard(events)))
-        // Find the guard with the highest sleep value for their sleepiest
-        // minute.
-        .max_by_key(|(_g, (_min, count))| *count)
-        .unwrap();
-    (guard, minute)
+        .filter_map(|s| s.report(&guard_to_events, bucket))
+        .collect()
 }
 
-/// Find the best guard and time to break in, with 2 strategies:
+/// Find the best guard and time to break in, with the 2 strategies of the
+/// puzzle:
 /// - Find the sleepiest guard, then the minute he is the most likely to be
-/// asleep.
+///   asleep (`SleepiestGuard`).
 /// - Find the guard that is the most likely to be asleep at a given minute, and
-/// that minute.
+///   that minute (`SleepiestBucket`).
 ///
-/// We return every time the product of the guard id and the minute in question.
+/// The answers are the products of the guard id and the minute in question,
+/// see `Report::answer`.
 ///
-/// The input is the lines of the log, unsorted.
-pub fn find_guard_and_time(lines: &[String]) -> (u32, u32) {
-    // Organize the logs.
-    let parsed_lines = parse_and_sort_lines(lines);
-    let day_events = summarize_by_day(parsed_lines);
-    let guard_to_events = summarize_by_guard(day_events);
-
-    // Strategy 1:
-    let sleepiest_guard = find_sleepiest_guard(&guard_to_events);
-    let sleepiest_minute =
-        find_sleepiest_minute_for_guard(&guard_to_events[&sleepiest_guard]).0;
-    println!(
-        "{:?} slept the most at minute {}",
-        sleepiest_guard,
-        sleepiest_minute
-    );
-
-    // Strategy 2:
-    let sleepiest_guard_at_minute = find_sleepiest_guard_at_minute(&guard_to_events);
-    println!(
-        "{:?} was the sleepiest guard at minute {}",
-        sleepiest_guard_at_minute.0,
-        sleepiest_guard_at_minute.1,
-    );
-
-    // Return the products of id * minute.
-    (
-        u32::from(sleepiest_guard) * sleepiest_minute,
-        u32::from(*sleepiest_guard_at_minute.0) * sleepiest_guard_at_minute.1,
-    )
+/// The input is the lines of the log, unsorted. The anomalies of the log are
+/// skipped, see `find_anomalies`.
+pub fn find_guard_and_time(lines: &[String]) -> Vec<Report> {
+    find_guard_and_bucket(lines, Bucket::MinuteOfDay)
+}
+
+/// Same as `find_guard_and_time`, but the naps are counted by `bucket` instead
+/// of by minute: the answers are the products of the guard id and the index of
+/// the bucket.
+pub fn find_guard_and_bucket(lines: &[String], bucket: Bucket) -> Vec<Report> {
+    run_strategies(lines, &[&SleepiestGuard, &SleepiestBucket], bucket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Example log from the subject, unsorted.
+    const EXAMPLE: &str = include_str!("../input_example");
+
+    fn example_lines() -> Vec<String> {
+        EXAMPLE.lines().map(|l| l.to_string()).collect()
+    }
+
+    /// The puzzle answers of each report.
+    fn answers(reports: &[Report]) -> Vec<u64> {
+        reports.iter().map(Report::answer).collect()
+    }
+
+    #[test]
+    fn test_find_guard_and_time() {
+        assert_eq!(vec![240, 4455], answers(&find_guard_and_time(&example_lines())));
+    }
+
+    #[test]
+    fn test_find_guard_and_bucket() {
+        // Guard 10 sleeps the most, mostly between 00:00 and 01:00; guard 99
+        // sleeps the most on the same hour.
+        assert_eq!(
+            vec![0, 0],
+            answers(&find_guard_and_bucket(&example_lines(), Bucket::HourOfDay))
+        );
+        // Guard 10 sleeps 45 minutes on Friday (4) and 5 on Sunday.
+        let reports = find_guard_and_bucket(&example_lines(), Bucket::Weekday);
+        assert_eq!(40, reports[0].answer());
+    }
+
+    #[test]
+    fn test_nap_across_midnight() {
+        let lines = vec![
+            "[1518-11-01 23:50] Guard #10 begins shift".to_string(),
+            "[1518-11-01 23:58] falls asleep".to_string(),
+            "[1518-11-02 00:03] wakes up".to_string(),
+            "[1518-11-02 23:55] Guard #10 begins shift".to_string(),
+            "[1518-11-02 23:59] falls asleep".to_string(),
+            "[1518-11-03 00:00] wakes up".to_string(),
+        ];
+        // Minute 1439 (23:59) is the only one slept twice.
+        assert_eq!(vec![14390, 14390], answers(&find_guard_and_time(&lines)));
+        assert_eq!(
+            vec![590, 590],
+            answers(&find_guard_and_bucket(&lines, Bucket::MinuteOfHour))
+        );
+    }
+
+    #[test]
+    fn test_run_strategies() {
+        let reports = run_strategies(
+            &example_lines(),
+            &[&LongestNap, &MostConsistentBucket],
+            Bucket::MinuteOfDay,
+        );
+        // Guard 10 naps from 00:30 to 00:55 on the first day.
+        assert_eq!(25, reports[0].score);
+        assert_eq!(300, reports[0].answer());
+        // Guard 10 is asleep at 00:24 on both of their shifts, and guard 99 at
+        // 00:45 on all 3 of theirs: the smallest ID wins.
+        assert_eq!(100, reports[1].score);
+        assert_eq!(240, reports[1].answer());
+        assert!(run_strategies(&[], &[&SleepiestGuard], Bucket::MinuteOfDay).is_empty());
+    }
+
+    #[test]
+    fn test_find_guard_and_time_skips_anomalies() {
+        let mut lines = example_lines();
+        lines.push("[1518-11-05 00:50] falls asleep".to_string());
+        lines.push("[1518-11-01 00:27] wakes up".to_string());
+        assert_eq!(vec![240, 4455], answers(&find_guard_and_time(&lines)));
+        assert_eq!(
+            vec![AnomalyKind::WakesWithoutSleeping, AnomalyKind::AlreadyAsleep],
+            find_anomalies(&lines)
+                .iter()
+                .map(|a| a.kind)
+                .collect::<Vec<_>>()
+        );
+    }
 }
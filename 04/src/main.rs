@@ -1,12 +1,80 @@
 extern crate lib;
+use std::env;
 use std::io;
 use std::io::BufRead;
 use std::string::String;
 
+use lib::{
+    Bucket, ChartFilter, GuardId, LongestNap, MostConsistentBucket, SleepiestBucket,
+    SleepiestGuard,
+};
+
+/// Parse the value of a command-line option, or exit.
+fn parse_option<T: std::str::FromStr>(name: &str, value: Option<String>) -> T {
+    match value.map(|v| v.parse()) {
+        Some(Ok(value)) => value,
+        _ => {
+            println!("Invalid or missing value for {}", name);
+            std::process::exit(2);
+        }
+    }
+}
+
 fn main() {
+    // With `--json`, print the reports as a JSON array instead of text.
+    let mut json = false;
+    // With `--chart`, print the chart of the shifts and the heat rows of the
+    // guards instead, optionally restricted with `--guard <id>`,
+    // `--from <yyyy-mm-dd>` and `--to <yyyy-mm-dd>`.
+    let mut chart = false;
+    let mut filter = ChartFilter::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--chart" => chart = true,
+            "--guard" => {
+                filter.guard = Some(GuardId::from(parse_option::<u32>(&arg, args.next())))
+            }
+            "--from" => filter.from = Some(parse_option(&arg, args.next())),
+            "--to" => filter.to = Some(parse_option(&arg, args.next())),
+            _ => {
+                println!("Unknown argument: {}", arg);
+                std::process::exit(2);
+            }
+        }
+    }
     let stdin = io::stdin();
     // Read the lines from stdin.
     let lines: Vec<String> = stdin.lock().lines().map(|l| l.unwrap()).collect();
-    // Print the result of the two strategies.
-    println!("{:?}", lib::find_guard_and_time(&lines));
+    // Report the problems in the log, they are skipped for the analysis.
+    for anomaly in lib::find_anomalies(&lines) {
+        eprintln!("Skipping anomaly: {}", anomaly);
+    }
+    if chart {
+        let days = lib::summarize_days(&lines);
+        print!("{}", lib::render_chart(&days, &filter));
+        println!();
+        print!("{}", lib::render_heat_rows(&days, &filter));
+        return;
+    }
+    // Run the two strategies of the puzzle, then the other ones.
+    let reports = lib::run_strategies(
+        &lines,
+        &[
+            &SleepiestGuard,
+            &SleepiestBucket,
+            &LongestNap,
+            &MostConsistentBucket,
+        ],
+        Bucket::MinuteOfDay,
+    );
+    if json {
+        let objects: Vec<String> = reports.iter().map(|r| r.to_json()).collect();
+        println!("[{}]", objects.join(","));
+    } else {
+        for report in reports {
+            println!("{}", report);
+        }
+    }
 }
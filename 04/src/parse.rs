@@ -15,6 +15,13 @@ impl From<GuardId> for u32 {
     }
 }
 
+/// Build a `GuardId` from a u32.
+impl From<u32> for GuardId {
+    fn from(id: u32) -> Self {
+        GuardId(id)
+    }
+}
+
 /// This represents one event of the log.
 #[derive(Debug, PartialEq, Eq)]
 pub enum GuardEvent {
@@ -39,7 +46,7 @@ named!(u32 <&str, u32>,
    map!(map!(digit, FromStr::from_str), Result::unwrap)
 );
 
-/// Parse a date from the log.
+// Parse a date from the log.
 named!(date <&str, NaiveDateTime>,
        do_parse!(
            char!('[') >>
@@ -53,10 +60,12 @@ named!(date <&str, NaiveDateTime>,
            char!(':') >>
            minute : u32 >>
            char!(']') >>
-           (NaiveDate::from_ymd(year, month, day).and_hms(hour, minute, 0))
+           date: expr_opt!(NaiveDate::from_ymd_opt(year, month, day)
+                           .and_then(|d| d.and_hms_opt(hour, minute, 0))) >>
+           (date)
 ));
 
-/// Parse a shift change event.
+// Parse a shift change event.
 named!(shift_begin <&str, GuardId>,
        do_parse!(
            tag!("Guard #") >>
@@ -65,25 +74,25 @@ named!(shift_begin <&str, GuardId>,
            (GuardId(id))
 ));
 
-/// Parse an event from the log.
+// Parse an event from the log.
 named!(guard_event <&str, GuardEvent>,
        alt!(
            tag!("falls asleep") => { |_| GuardEvent::FallsAsleep } |
            tag!("wakes up") => { |_| GuardEvent::WakesUp } |
-           shift_begin => { |id| GuardEvent::ShiftBegin(id) }
+           shift_begin => { GuardEvent::ShiftBegin }
 ));
 
-/// Parse a line from the log.
+// Parse a line from the log.
 named!(parse_event <&str, DatedEvent>,
        do_parse!(
            date: date >>
            char!(' ') >>
            event: guard_event >>
-           ( DatedEvent { date: date, event: event} )
+           ( DatedEvent { date, event } )
 ));
 
 /// Parse a `line` from the log, panics if it fails.
-#[allow(clippy::stutter)]
+#[allow(clippy::module_name_repetitions)]
 pub fn parse_line(line: &str) -> DatedEvent {
     parse_event(line).unwrap().1
 }
@@ -126,7 +135,10 @@ mod tests {
                 .unwrap()
                 .1,
             DatedEvent {
-                date: NaiveDate::from_ymd(1518, 9, 1).and_hms(23, 56, 0),
+                date: NaiveDate::from_ymd_opt(1518, 9, 1)
+                    .unwrap()
+                    .and_hms_opt(23, 56, 0)
+                    .unwrap(),
                 event: GuardEvent::ShiftBegin(GuardId(1019)),
             }
         );
@@ -0,0 +1,425 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::histogram::{build_sleep_histogram, Bucket};
+use crate::parse::GuardId;
+use crate::{time_slept, DayEvent};
+
+/// A way of choosing the guard to sneak past, and when.
+///
+/// Implementors only have to pick a guard and a bucket, the report is built by `Strategy::report`.
+/// For example, to pick the guard who slept during the most shifts:
+///
+/// ```
+/// use std::collections::HashMap;
+/// use lib::{Bucket, DayEvent, GuardId, Strategy};
+///
+/// struct MostNappingShifts;
+///
+/// impl Strategy for MostNappingShifts {
+///     fn name(&self) -> &str {
+///         "most napping shifts"
+///     }
+///
+///     fn choose(
+///         &self,
+///         events_by_guard: &HashMap<GuardId, Vec<DayEvent>>,
+///         bucket: Bucket,
+///     ) -> Option<(GuardId, usize, i64)> {
+///         let (guard, events) = events_by_guard.iter().max_by_key(|(_, events)| {
+///             events.iter().filter(|e| !e.sleep_times.is_empty()).count()
+///         })?;
+///         let start = events.iter().flat_map(|e| &e.sleep_times).next()?.0;
+///         Some((*guard, bucket.index(start), events.len() as i64))
+///     }
+/// }
+///
+/// let lines = vec![
+///     "[1518-11-01 00:00] Guard #10 begins shift".to_string(),
+///     "[1518-11-01 00:05] falls asleep".to_string(),
+///     "[1518-11-01 00:25] wakes up".to_string(),
+/// ];
+/// let summary = lib::summarize_log(&lines);
+/// let report = MostNappingShifts.report(&summary, Bucket::MinuteOfDay).unwrap();
+/// assert_eq!(50, report.answer());
+/// ```
+pub trait Strategy {
+    /// Short description of the strategy, used in the reports.
+    fn name(&self) -> &str;
+
+    /// Choose a guard and the index of a bucket, along with the score that made them win.
+    ///
+    /// Return `None` if there is nothing to choose from (e.g. no guard ever slept).
+    fn choose(
+        &self,
+        events_by_guard: &HashMap<GuardId, Vec<DayEvent>>,
+        bucket: Bucket,
+    ) -> Option<(GuardId, usize, i64)>;
+
+    /// Choose a guard and a bucket, and gather the numbers about them in a report.
+    fn report(
+        &self,
+        events_by_guard: &HashMap<GuardId, Vec<DayEvent>>,
+        bucket: Bucket,
+    ) -> Option<Report> {
+        let (guard, index, score) = self.choose(events_by_guard, bucket)?;
+        let events = events_by_guard
+            .get(&guard)
+            .map(|events| events.as_slice())
+            .unwrap_or_default();
+        Some(Report {
+            strategy: self.name().to_string(),
+            guard,
+            bucket,
+            index,
+            score,
+            asleep_in_bucket: build_sleep_histogram(events, bucket)[index],
+            minutes_asleep: time_slept(events),
+            shifts: events.len(),
+        })
+    }
+}
+
+/// The result of a strategy: the chosen guard and bucket, and the numbers backing the choice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// Name of the strategy.
+    pub strategy: String,
+    pub guard: GuardId,
+    pub bucket: Bucket,
+    /// Index of the chosen bucket, e.g. the minute of the day.
+    pub index: usize,
+    /// The value the strategy maximized; its meaning depends on the strategy.
+    pub score: i64,
+    /// Number of minutes the guard was asleep in the chosen bucket, over all their shifts. With
+    /// minute buckets, this is the number of shifts.
+    pub asleep_in_bucket: u32,
+    /// Total number of minutes the guard was asleep.
+    pub minutes_asleep: i64,
+    /// Number of shifts of the guard.
+    pub shifts: usize,
+}
+
+impl Report {
+    /// The puzzle answer: the guard ID multiplied by the bucket index.
+    pub fn answer(&self) -> u64 {
+        u64::from(u32::from(self.guard)) * self.index as u64
+    }
+
+    /// Format the report as a JSON object.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"strategy\":{},\"guard\":{},\"bucket\":\"{:?}\",\"index\":{},\"score\":{},\
+             \"asleep_in_bucket\":{},\"minutes_asleep\":{},\"shifts\":{},\"answer\":{}}}",
+            json_string(&self.strategy),
+            u32::from(self.guard),
+            self.bucket,
+            self.index,
+            self.score,
+            self.asleep_in_bucket,
+            self.minutes_asleep,
+            self.shifts,
+            self.answer()
+        )
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: guard #{} at {:?} {} (score {}, asleep {} minutes there, {} minutes over {} \
+             shifts), answer {}",
+            self.strategy,
+            u32::from(self.guard),
+            self.bucket,
+            self.index,
+            self.score,
+            self.asleep_in_bucket,
+            self.minutes_asleep,
+            self.shifts,
+            self.answer()
+        )
+    }
+}
+
+/// Quote and escape a string for JSON.
+fn json_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+/// The guards sorted by ID, so that ties are always broken the same way (in favor of the
+/// smallest ID).
+fn sorted_guards(
+    events_by_guard: &HashMap<GuardId, Vec<DayEvent>>,
+) -> Vec<(GuardId, &[DayEvent])> {
+    let mut guards = events_by_guard
+        .iter()
+        .map(|(guard, events)| (*guard, events.as_slice()))
+        .collect::<Vec<_>>();
+    guards.sort_by_key(|(guard, _)| u32::from(*guard));
+    guards
+}
+
+/// Given a single guard, find the bucket (e.g. minute) where he was most often asleep, and how
+/// often. Ties are broken in favor of the earliest bucket.
+fn find_sleepiest_bucket_for_guard(events: &[DayEvent], bucket: Bucket) -> (usize, u32) {
+    let histogram = build_sleep_histogram(events, bucket);
+    let (index, value) = histogram
+        .iter()
+        .enumerate()
+        // Max count per minute.
+        .max_by_key(|(index, count)| (*count, Reverse(*index)))
+        .unwrap();
+    (index, *value)
+}
+
+/// Strategy 1 of the puzzle: find the sleepiest guard, then the bucket (e.g. minute) he is the
+/// most likely to be asleep.
+///
+/// The score is the total number of minutes the guard slept.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SleepiestGuard;
+
+impl Strategy for SleepiestGuard {
+    fn name(&self) -> &str {
+        "sleepiest guard"
+    }
+
+    fn choose(
+        &self,
+        events_by_guard: &HashMap<GuardId, Vec<DayEvent>>,
+        bucket: Bucket,
+    ) -> Option<(GuardId, usize, i64)> {
+        let (guard, events, slept) = sorted_guards(events_by_guard)
+            .into_iter()
+            .map(|(guard, events)| (guard, events, time_slept(events)))
+            .max_by_key(|(guard, _, slept)| (*slept, Reverse(u32::from(*guard))))?;
+        if slept == 0 {
+            return None;
+        }
+        let (index, _) = find_sleepiest_bucket_for_guard(events, bucket);
+        Some((guard, index, slept))
+    }
+}
+
+/// Strategy 2 of the puzzle: find the guard that is the most likely to be asleep at a specific
+/// bucket (e.g. minute), along with that bucket.
+///
+/// The score is the number of minutes the guard was asleep in the bucket.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SleepiestBucket;
+
+impl Strategy for SleepiestBucket {
+    fn name(&self) -> &str {
+        "sleepiest bucket"
+    }
+
+    fn choose(
+        &self,
+        events_by_guard: &HashMap<GuardId, Vec<DayEvent>>,
+        bucket: Bucket,
+    ) -> Option<(GuardId, usize, i64)> {
+        let (guard, (index, count)) = sorted_guards(events_by_guard)
+            .into_iter()
+            // For each guard find the bucket they are most likely to be sleeping.
+            .map(|(guard, events)| (guard, find_sleepiest_bucket_for_guard(events, bucket)))
+            // Find the guard with the highest sleep value for their sleepiest bucket.
+            .max_by_key(|(guard, (_, count))| (*count, Reverse(u32::from(*guard))))?;
+        if count == 0 {
+            return None;
+        }
+        Some((guard, index, i64::from(count)))
+    }
+}
+
+/// Find the guard who took the longest single nap, and the bucket at which that nap started.
+///
+/// The score is the length of the nap, in minutes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LongestNap;
+
+impl Strategy for LongestNap {
+    fn name(&self) -> &str {
+        "longest nap"
+    }
+
+    fn choose(
+        &self,
+        events_by_guard: &HashMap<GuardId, Vec<DayEvent>>,
+        bucket: Bucket,
+    ) -> Option<(GuardId, usize, i64)> {
+        sorted_guards(events_by_guard)
+            .into_iter()
+            .flat_map(|(guard, events)| {
+                events
+                    .iter()
+                    .flat_map(|e| &e.sleep_times)
+                    .map(move |(start, end)| (guard, *start, (*end - *start).num_minutes()))
+            })
+            // The earliest of the longest naps of the guard with the smallest ID.
+            .max_by_key(|(guard, start, length)| {
+                (*length, Reverse(u32::from(*guard)), Reverse(*start))
+            })
+            .map(|(guard, start, length)| (guard, bucket.index(start), length))
+    }
+}
+
+/// Find the guard and bucket such that the guard was asleep in that bucket on the largest share
+/// of their shifts, regardless of how many shifts they had.
+///
+/// The score is the number of minutes asleep in the bucket per 100 shifts: with minute buckets,
+/// it is the percentage of the shifts during which the guard was asleep at that minute.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MostConsistentBucket;
+
+impl Strategy for MostConsistentBucket {
+    fn name(&self) -> &str {
+        "most consistent bucket"
+    }
+
+    fn choose(
+        &self,
+        events_by_guard: &HashMap<GuardId, Vec<DayEvent>>,
+        bucket: Bucket,
+    ) -> Option<(GuardId, usize, i64)> {
+        let (guard, index, score) = sorted_guards(events_by_guard)
+            .into_iter()
+            .filter(|(_, events)| !events.is_empty())
+            .map(|(guard, events)| {
+                let (index, count) = find_sleepiest_bucket_for_guard(events, bucket);
+                #[allow(clippy::cast_possible_wrap)]
+                (guard, index, i64::from(count) * 100 / events.len() as i64)
+            })
+            .max_by_key(|(guard, _, score)| (*score, Reverse(u32::from(*guard))))?;
+        if score == 0 {
+            return None;
+        }
+        Some((guard, index, score))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::chrono::NaiveDateTime;
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%F %R").unwrap()
+    }
+
+    fn day_event(guard: u32, naps: &[(&str, &str)]) -> DayEvent {
+        DayEvent {
+            guard: GuardId::from(guard),
+            sleep_times: naps.iter().map(|(s, e)| (time(s), time(e))).collect(),
+            ..DayEvent::default()
+        }
+    }
+
+    /// Guard 10 sleeps a lot but at various times, guard 20 always sleeps at 00:30 during its
+    /// only shift, guard 30 takes a single long nap.
+    fn example_summary() -> HashMap<GuardId, Vec<DayEvent>> {
+        let events = vec![
+            day_event(10, &[("1518-11-01 00:05", "1518-11-01 00:25")]),
+            day_event(10, &[("1518-11-02 00:20", "1518-11-02 00:40")]),
+            day_event(10, &[]),
+            day_event(20, &[("1518-11-04 00:30", "1518-11-04 00:32")]),
+            day_event(30, &[("1518-11-05 00:10", "1518-11-05 00:35")]),
+        ];
+        crate::summarize_by_guard(events)
+    }
+
+    fn choose(strategy: &dyn Strategy) -> Option<(u32, usize, i64)> {
+        strategy
+            .choose(&example_summary(), Bucket::MinuteOfHour)
+            .map(|(guard, index, score)| (u32::from(guard), index, score))
+    }
+
+    #[test]
+    fn test_strategies() {
+        assert_eq!(Some((10, 20, 40)), choose(&SleepiestGuard));
+        // Guard 10 is asleep on 2 days from minute 20 to 24, the first one wins.
+        assert_eq!(Some((10, 20, 2)), choose(&SleepiestBucket));
+        assert_eq!(Some((30, 10, 25)), choose(&LongestNap));
+        // Guards 20 and 30 are asleep at some minute during all their shifts, the smallest ID
+        // wins.
+        assert_eq!(Some((20, 30, 100)), choose(&MostConsistentBucket));
+    }
+
+    #[test]
+    fn test_nobody_slept() {
+        let summary = crate::summarize_by_guard(vec![day_event(10, &[])]);
+        for strategy in &[
+            &SleepiestGuard as &dyn Strategy,
+            &SleepiestBucket,
+            &LongestNap,
+            &MostConsistentBucket,
+        ] {
+            assert_eq!(None, strategy.report(&summary, Bucket::MinuteOfDay));
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let report = SleepiestGuard
+            .report(&example_summary(), Bucket::MinuteOfHour)
+            .unwrap();
+        assert_eq!(
+            Report {
+                strategy: "sleepiest guard".to_string(),
+                guard: GuardId::from(10),
+                bucket: Bucket::MinuteOfHour,
+                index: 20,
+                score: 40,
+                asleep_in_bucket: 2,
+                minutes_asleep: 40,
+                shifts: 3,
+            },
+            report
+        );
+        assert_eq!(200, report.answer());
+        assert_eq!(
+            "{\"strategy\":\"sleepiest guard\",\"guard\":10,\"bucket\":\"MinuteOfHour\",\
+             \"index\":20,\"score\":40,\"asleep_in_bucket\":2,\"minutes_asleep\":40,\
+             \"shifts\":3,\"answer\":200}",
+            report.to_json()
+        );
+        assert_eq!(
+            "sleepiest guard: guard #10 at MinuteOfHour 20 (score 40, asleep 2 minutes there, \
+             40 minutes over 3 shifts), answer 200",
+            report.to_string()
+        );
+    }
+
+    #[test]
+    fn test_large_answer() {
+        let report = Report {
+            strategy: "sleepiest guard".to_string(),
+            guard: GuardId::from(u32::MAX),
+            bucket: Bucket::MinuteOfDay,
+            index: 1439,
+            score: 1,
+            asleep_in_bucket: 1,
+            minutes_asleep: 1,
+            shifts: 1,
+        };
+        assert_eq!(u64::from(u32::MAX) * 1439, report.answer());
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!("\"a \\\"b\\\" \\\\ \\u000a\"", json_string("a \"b\" \\ \n"));
+    }
+}
@@ -0,0 +1,219 @@
+use std::fmt;
+
+use crate::parse::chrono::NaiveDateTime;
+use crate::parse::{DatedEvent, GuardEvent, GuardId};
+
+/// Kind of problem found in the log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnomalyKind {
+    /// A guard falls asleep or wakes up before any shift began.
+    EventBeforeFirstShift,
+    /// The guard falls asleep while already asleep.
+    AlreadyAsleep,
+    /// The guard wakes up without having fallen asleep.
+    WakesWithoutSleeping,
+    /// The guard is still asleep when the next shift begins (or at the end of the log).
+    AsleepAtShiftEnd,
+    /// Another event happened at the exact same time.
+    DuplicateTimestamp,
+}
+
+impl fmt::Display for AnomalyKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            AnomalyKind::EventBeforeFirstShift => "event before the first shift",
+            AnomalyKind::AlreadyAsleep => "falls asleep while already asleep",
+            AnomalyKind::WakesWithoutSleeping => "wakes up without sleeping",
+            AnomalyKind::AsleepAtShiftEnd => "still asleep at the end of the shift",
+            AnomalyKind::DuplicateTimestamp => "duplicate timestamp",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+/// A problem found in the log, with the time of the offending event and the guard on duty (if
+/// any).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anomaly {
+    pub date: NaiveDateTime,
+    pub guard: Option<GuardId>,
+    pub kind: AnomalyKind,
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.date.format("[%F %R]"))?;
+        if let Some(guard) = self.guard {
+            write!(f, " guard #{}", u32::from(guard))?;
+        }
+        write!(f, ": {}", self.kind)
+    }
+}
+
+/// Check the sorted events of the log, and split them into a clean list of events, where every
+/// guard falls asleep and wakes up in turn during their shift, and the list of anomalies.
+///
+/// The offending events are dropped: the second of two events with the same time, sleep events
+/// outside of a shift, and unmatched sleep or wake events.
+pub fn validate_events(events: Vec<DatedEvent>) -> (Vec<DatedEvent>, Vec<Anomaly>) {
+    let mut clean = Vec::with_capacity(events.len());
+    let mut anomalies = Vec::new();
+    // Guard currently on duty.
+    let mut guard: Option<GuardId> = None;
+    // Position in `clean` of the event where the guard fell asleep, if they are asleep.
+    let mut asleep: Option<usize> = None;
+    // Time of the previous event, valid or not.
+    let mut last_date: Option<NaiveDateTime> = None;
+    // Close the current shift, dropping the sleep event if the guard is still asleep.
+    let end_shift = |clean: &mut Vec<DatedEvent>,
+                     anomalies: &mut Vec<Anomaly>,
+                     guard: Option<GuardId>,
+                     asleep: Option<usize>| {
+        if let Some(index) = asleep {
+            let event = clean.remove(index);
+            anomalies.push(Anomaly {
+                date: event.date,
+                guard,
+                kind: AnomalyKind::AsleepAtShiftEnd,
+            });
+        }
+    };
+    for event in events {
+        let anomaly = |kind| Anomaly {
+            date: event.date,
+            guard,
+            kind,
+        };
+        if last_date.replace(event.date) == Some(event.date) {
+            // A dropped shift start is reported against the guard it names.
+            let guard = match event.event {
+                GuardEvent::ShiftBegin(id) => Some(id),
+                _ => guard,
+            };
+            anomalies.push(Anomaly {
+                date: event.date,
+                guard,
+                kind: AnomalyKind::DuplicateTimestamp,
+            });
+            continue;
+        }
+        match event.event {
+            GuardEvent::ShiftBegin(id) => {
+                end_shift(&mut clean, &mut anomalies, guard, asleep.take());
+                guard = Some(id);
+            }
+            GuardEvent::FallsAsleep if guard.is_none() => {
+                anomalies.push(anomaly(AnomalyKind::EventBeforeFirstShift));
+                continue;
+            }
+            GuardEvent::FallsAsleep if asleep.is_some() => {
+                anomalies.push(anomaly(AnomalyKind::AlreadyAsleep));
+                continue;
+            }
+            GuardEvent::FallsAsleep => asleep = Some(clean.len()),
+            GuardEvent::WakesUp if guard.is_none() => {
+                anomalies.push(anomaly(AnomalyKind::EventBeforeFirstShift));
+                continue;
+            }
+            GuardEvent::WakesUp if asleep.is_none() => {
+                anomalies.push(anomaly(AnomalyKind::WakesWithoutSleeping));
+                continue;
+            }
+            GuardEvent::WakesUp => asleep = None,
+        }
+        clean.push(event);
+    }
+    end_shift(&mut clean, &mut anomalies, guard, asleep);
+    anomalies.sort_by_key(|a| a.date);
+    (clean, anomalies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_line;
+
+    fn parse(lines: &[&str]) -> Vec<DatedEvent> {
+        lines.iter().map(|l| parse_line(l)).collect()
+    }
+
+    fn anomaly(date: &str, guard: Option<u32>, kind: AnomalyKind) -> Anomaly {
+        Anomaly {
+            date: NaiveDateTime::parse_from_str(date, "%F %R").unwrap(),
+            guard: guard.map(GuardId::from),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_clean_log() {
+        let events = parse(&[
+            "[1518-11-01 00:00] Guard #10 begins shift",
+            "[1518-11-01 00:05] falls asleep",
+            "[1518-11-01 00:25] wakes up",
+        ]);
+        let (clean, anomalies) = validate_events(events);
+        assert_eq!(3, clean.len());
+        assert!(anomalies.is_empty());
+    }
+
+    #[test]
+    fn test_anomalies() {
+        let events = parse(&[
+            "[1518-10-31 23:50] falls asleep",
+            "[1518-11-01 00:00] Guard #10 begins shift",
+            "[1518-11-01 00:05] falls asleep",
+            "[1518-11-01 00:05] wakes up",
+            "[1518-11-01 00:10] falls asleep",
+            "[1518-11-01 00:25] wakes up",
+            "[1518-11-01 00:26] wakes up",
+            "[1518-11-01 00:30] falls asleep",
+            "[1518-11-02 00:00] Guard #99 begins shift",
+            "[1518-11-02 00:10] falls asleep",
+        ]);
+        let (clean, anomalies) = validate_events(events);
+        assert_eq!(
+            parse(&[
+                "[1518-11-01 00:00] Guard #10 begins shift",
+                "[1518-11-01 00:05] falls asleep",
+                "[1518-11-01 00:25] wakes up",
+                "[1518-11-02 00:00] Guard #99 begins shift",
+            ]),
+            clean
+        );
+        assert_eq!(
+            vec![
+                anomaly("1518-10-31 23:50", None, AnomalyKind::EventBeforeFirstShift),
+                anomaly("1518-11-01 00:05", Some(10), AnomalyKind::DuplicateTimestamp),
+                anomaly("1518-11-01 00:10", Some(10), AnomalyKind::AlreadyAsleep),
+                anomaly("1518-11-01 00:26", Some(10), AnomalyKind::WakesWithoutSleeping),
+                anomaly("1518-11-01 00:30", Some(10), AnomalyKind::AsleepAtShiftEnd),
+                anomaly("1518-11-02 00:10", Some(99), AnomalyKind::AsleepAtShiftEnd),
+            ],
+            anomalies
+        );
+    }
+
+    #[test]
+    fn test_duplicate_shift_begin() {
+        let events = parse(&[
+            "[1518-11-01 00:00] Guard #10 begins shift",
+            "[1518-11-02 00:00] Guard #10 begins shift",
+            "[1518-11-02 00:00] Guard #99 begins shift",
+        ]);
+        let (clean, anomalies) = validate_events(events);
+        assert_eq!(2, clean.len());
+        assert_eq!(
+            vec![anomaly("1518-11-02 00:00", Some(99), AnomalyKind::DuplicateTimestamp)],
+            anomalies
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            "[1518-11-01 00:26] guard #10: wakes up without sleeping",
+            anomaly("1518-11-01 00:26", Some(10), AnomalyKind::WakesWithoutSleeping).to_string()
+        );
+    }
+}
@@ -1,22 +1,15 @@
-extern crate linked_list;
+mod reducer;
+mod removal;
+mod rules;
+mod trace;
 
-use linked_list::{LinkedList, Cursor};
+pub use crate::reducer::{reduce_stream, trace_reactions, Reducer};
+pub use crate::removal::{removal_report, removal_report_with, Removal, RemovalReport};
+pub use crate::rules::{CaseRules, Reaction, ReactionRules, ReactionTable, RulesError};
+pub use crate::trace::{Trace, TraceEvent};
 
 const CASE_DIFF: u8 = b'a' - b'A';
 
-/// Transform the (ascii) input into a list, optionally skipping a unit.
-/// The letters to be skipped are the one in except (must be within A-Z) and the
-/// lowercase version of it.
-fn to_linked_list_without(bytes: &[u8], except: Option<u8>) -> LinkedList<u8> {
-    let mut list = LinkedList::new();
-    for byte in bytes {
-        if *byte != b'\n' && Some(*byte) != except && Some(*byte - CASE_DIFF) != except {
-            list.push_back(byte.clone());
-        }
-    }
-    list
-}
-
 /// Check if v1 is the lowercase version of v2.
 fn is_pair_checked(v1: u8, v2: u8) -> bool {
     match v1.checked_sub(v2) {
@@ -33,64 +26,57 @@ fn is_pair(e1: Option<u8>, e2: Option<u8>) -> bool {
     }
 }
 
-/// Clone a mutable element option into an owning version.
-fn clone<T: Clone>(element: Option<&mut T>) -> Option<T> {
-    match element {
-        None => None,
-        Some(e) => Some(e.clone()),
-    }
-}
-
-/// Meat of the algorithm. This takes a cursor (that sits between 2 elements)
-/// that is before the first element of the list. It will go through the list
-/// and remove all pairs (e.g. "aA" or "Pp").
-fn remove_pairs_from_list<'a>(cursor: &mut Cursor<'a, u8>) {
-    // Advance through the list one by one.
-    while cursor.next() != None {
-        // If the cursor is in the middle of a pair, remove the pair, and check
-        // whether the cursor is in the middle of a pair again in case we
-        // created a pair.
-        while is_pair(clone(cursor.peek_prev()), clone(cursor.peek_next())) {
-            // Remove the element after the cursor.
-            cursor.remove();
-            // Step back one element.
-            cursor.seek_backward(1);
-            // Remove the first element of the pair.
-            cursor.remove();
-        }
-    }
-}
-
-/// Transform a list of bytes into a string.
-fn string_from_list(list: &LinkedList<u8>) -> std::string::String {
-    let vec: Vec<u8> = list.clone().into_iter().collect();
-    std::str::from_utf8(&vec).unwrap().to_string()
-}
-
-/// Remove all the pairs in the given input, ignoring the letters specified by
-/// unit: if None, then don't ignore letters, if Some(e), then ignore e and
-/// lowercase(e). e must be an uppercase letter.
+/// Remove all the pairs in the given input, ignoring the units specified by
+/// unit: if None, then don't ignore units, if Some(e), then ignore the units
+/// of type e (see `ReactionRules::unit_type`).
 /// It returns the resulting string, after removal.
-fn remove_pairs_without(bytes: &[u8], unit: Option<u8>) -> std::string::String {
-    // Construct the list without the forbidden letters.
-    let mut list = to_linked_list_without(bytes, unit);
-    // Sanity check: the list has an even length.
-    assert_eq!(list.len() % 2, 0);
-    // Remove the adjacent pairs of letters.
-    remove_pairs_from_list(&mut list.cursor());
-    string_from_list(&list)
+fn remove_pairs_without<R: ReactionRules>(
+    bytes: &[u8],
+    rules: R,
+    unit: Option<u8>,
+) -> std::string::String {
+    let mut reducer = Reducer::with_rules(rules, unit);
+    // Remove the adjacent pairs of units.
+    reducer.extend(bytes);
+    reducer.into_string()
 }
 
 /// Remove the pairs from the input, returns the minimal string.
 pub fn remove_pairs(bytes: &[u8]) -> std::string::String {
-    remove_pairs_without(bytes, None)
+    remove_pairs_with(bytes, CaseRules)
+}
+
+/// Let the units of the input react according to the rules, returns the
+/// resulting string.
+pub fn remove_pairs_with<R: ReactionRules>(bytes: &[u8], rules: R) -> std::string::String {
+    remove_pairs_without(bytes, rules, None)
 }
 
 /// For each letter, try to remove all the pairs from the input without that
 /// letter. Returns the size of the minimal string.
+///
+/// See `removal_report` for the details.
 pub fn try_remove_pairs(bytes: &[u8]) -> usize {
-    (b'A'..=b'Z')
-        .map(|c| remove_pairs_without(bytes, Some(c)).len())
+    removal_report(bytes, 1).best().map_or(0, |r| r.length)
+}
+
+/// For each type of unit in the input, let the units react according to the
+/// rules without the units of that type. Returns the size of the minimal
+/// string.
+///
+/// Unlike `try_remove_pairs`, the input is not reduced first, as the result of
+/// arbitrary rules may depend on the order of the reactions.
+pub fn try_remove_pairs_with<R: ReactionRules>(bytes: &[u8], rules: R) -> usize {
+    let mut unit_types: Vec<u8> = bytes
+        .iter()
+        .filter(|b| **b != b'\n')
+        .map(|b| rules.unit_type(*b))
+        .collect();
+    unit_types.sort_unstable();
+    unit_types.dedup();
+    unit_types
+        .into_iter()
+        .map(|t| remove_pairs_without(bytes, &rules, Some(t)).len())
         .min()
         .unwrap_or(0)
 }
@@ -101,10 +87,10 @@ mod tests {
 
     #[test]
     fn test_is_pair() {
-        assert_eq!(is_pair(None, Some(32)), false);
-        assert_eq!(is_pair(Some(b'a'), Some(b'A')), true);
-        assert_eq!(is_pair(Some(b'A'), Some(b'a')), true);
-        assert_eq!(is_pair(Some(b'a'), Some(b'B')), false);
+        assert!(!is_pair(None, Some(32)));
+        assert!(is_pair(Some(b'a'), Some(b'A')));
+        assert!(is_pair(Some(b'A'), Some(b'a')));
+        assert!(!is_pair(Some(b'a'), Some(b'B')));
     }
 
     fn prepare_input(string: &str) -> Vec<u8> {
@@ -123,8 +109,29 @@ mod tests {
         );
         assert_eq!(remove_pairs(&prepare_input("abcdDCBA")), "".to_string());
     }
+    #[test]
+    fn test_reduce_stream_same_as_remove_pairs() {
+        for input in &["aA", "abAB", "dabAcCaCBAcCcaDA\n", "aAbBcCd"] {
+            let reduced = reduce_stream(input.as_bytes()).unwrap();
+            assert_eq!(remove_pairs(input.as_bytes()).into_bytes(), reduced);
+        }
+    }
+
     #[test]
     fn test_try_remove_pairs_from_list() {
         assert_eq!(try_remove_pairs(&prepare_input("dabAcCaCBAcCcaDA")), 4);
     }
+
+    #[test]
+    fn test_remove_pairs_with_table() {
+        // Only lowercase followed by uppercase react, "x" and "y" make a "z".
+        let table =
+            ReactionTable::from_reader("aA\nbB\ncC\ndD\nxy -> z\n".as_bytes()).unwrap();
+        let input = prepare_input("dabAcCaCBAcCcaDA");
+        assert_eq!(remove_pairs_with(&input, &table), "dabAaCBAcaDA".to_string());
+        assert_eq!(remove_pairs_with(b"axyA", &table), "azA".to_string());
+        // Every unit is its own type: the best is to remove the "A" units, but
+        // not the "a" ones, which leaves "dabaCBcaD".
+        assert_eq!(try_remove_pairs_with(&input, &table), 9);
+    }
 }
@@ -1,18 +1,125 @@
+use std::env;
+use std::fs::File;
 use std::io;
-use std::io::Read;
+use std::io::{BufReader, Read};
 
+use lib::{ReactionRules, ReactionTable, Reducer, RemovalReport};
 
-fn main() {
+/// Print the length for each removal of the report.
+fn print_report(report: &RemovalReport) {
+    for removal in &report.removals {
+        println!(
+            "Without {}: {}",
+            String::from_utf8_lossy(&removal.units),
+            removal.length
+        );
+    }
+}
+
+/// Write the trace of the reducer as JSON to the file, if tracing is enabled.
+fn write_trace<R: ReactionRules>(reducer: &Reducer<R>, trace_path: Option<&str>) {
+    if let (Some(path), Some(trace)) = (trace_path, reducer.trace()) {
+        if let Err(err) = std::fs::write(path, trace.to_json()) {
+            println!("Error writing trace: {}", err);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Print the results with the rules of the puzzle, reducing the input while
+/// reading it.
+fn run_with_case_rules(report_size: Option<usize>, trace_path: Option<&str>) {
     let stdin = io::stdin();
-    let mut buffer = Vec::new();
-    // Read the input as a vector of bytes.
-    stdin.lock().read_to_end(&mut buffer).unwrap();
+    let mut reducer = Reducer::new();
+    if trace_path.is_some() {
+        reducer = reducer.with_trace();
+    }
+    if let Err(err) = reducer.read_from(stdin.lock()) {
+        println!("Error reading input: {}", err);
+        std::process::exit(2);
+    }
+    write_trace(&reducer, trace_path);
+    let reduced = reducer.as_bytes();
+    println!("Characters left in the list: {}", reduced.len());
+    // The reactions don't depend on the order in which they happen, so
+    // removing a unit from the reduced polymer gives the same result as
+    // removing it from the input.
     println!(
-        "Characters left in the list: {}",
-        lib::remove_pairs(&buffer).len()
+        "Characters left without worst unit: {}",
+        lib::try_remove_pairs(reduced)
     );
+    if let Some(size) = report_size {
+        print_report(&lib::removal_report(reduced, size));
+    }
+}
+
+/// Print the results with the given reaction table. With arbitrary rules, the
+/// result may depend on the order of the reactions, so the units are always
+/// removed from the whole input.
+fn run_with_table(table: &ReactionTable, report_size: Option<usize>, trace_path: Option<&str>) {
+    let stdin = io::stdin();
+    let mut buffer = Vec::new();
+    // Read the input as a vector of bytes.
+    if let Err(err) = stdin.lock().read_to_end(&mut buffer) {
+        println!("Error reading input: {}", err);
+        std::process::exit(2);
+    }
+    let mut reducer = Reducer::with_rules(table, None);
+    if trace_path.is_some() {
+        reducer = reducer.with_trace();
+    }
+    reducer.extend(&buffer);
+    write_trace(&reducer, trace_path);
+    println!("Characters left in the list: {}", reducer.len());
     println!(
         "Characters left without worst unit: {}",
-        lib::try_remove_pairs(&buffer)
+        lib::try_remove_pairs_with(&buffer, table)
     );
+    if let Some(size) = report_size {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        print_report(&lib::removal_report_with(&buffer, table, size, threads));
+    }
+}
+
+/// Print the usage and exit.
+fn usage() -> ! {
+    println!("Usage: day_05 [--rules <file>] [--report <max units>] [--trace <file.json>]");
+    std::process::exit(2);
+}
+
+fn main() {
+    // With `--rules <file>`, load the reaction table from the file instead of
+    // using the rules of the puzzle.
+    let mut rules_path = None;
+    // With `--report <k>`, also print the length without every set of up to k
+    // unit types.
+    let mut report_size = None;
+    // With `--trace <file>`, write the reactions to the file, as JSON.
+    let mut trace_path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--rules", Some(path)) => rules_path = Some(path),
+            ("--trace", Some(path)) => trace_path = Some(path),
+            ("--report", Some(size)) => match size.parse() {
+                Ok(size) => report_size = Some(size),
+                Err(_) => usage(),
+            },
+            _ => usage(),
+        }
+    }
+    let path = match rules_path {
+        None => return run_with_case_rules(report_size, trace_path.as_deref()),
+        Some(path) => path,
+    };
+    let table = File::open(&path)
+        .map_err(lib::RulesError::from)
+        .and_then(|f| ReactionTable::from_reader(BufReader::new(f)));
+    match table {
+        Ok(table) => run_with_table(&table, report_size, trace_path.as_deref()),
+        Err(err) => {
+            println!("Error reading rules: {}", err);
+            std::process::exit(2);
+        }
+    }
 }
@@ -0,0 +1,251 @@
+use std::io;
+use std::io::Read;
+
+use crate::rules::{CaseRules, Reaction, ReactionRules};
+use crate::trace::{Recorder, Trace};
+
+/// Size of the chunks read from the input stream.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Polymer reducer that consumes the units one by one, and only keeps the
+/// units that have not reacted (yet).
+///
+/// The reduced polymer is kept as a stack: a new unit either reacts with the
+/// unit on top of the stack, and both disappear, or is pushed on top. A unit
+/// that is not on top of the stack can only react once all the units after it
+/// are gone, which is exactly when it gets back on top. This is a single pass
+/// over the input, and the memory used is the length of the reduced polymer.
+///
+/// The reactions follow the `ReactionRules`, by default the case rules of the
+/// puzzle. When a pair transforms into another unit, the new unit may react in
+/// turn with the unit before it: the reactions always happen from left to
+/// right, as soon as the units are adjacent.
+///
+/// For example:
+/// ```
+/// let mut reducer = lib::Reducer::new();
+/// reducer.extend(b"dabAcCaC");
+/// reducer.extend(b"BAcCcaDA");
+/// assert_eq!(reducer.as_bytes(), b"dabCBAcaDA");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Reducer<R = CaseRules> {
+    /// The units left after the reactions, in order.
+    stack: Vec<u8>,
+    /// Type of the units to ignore, if any.
+    except: Option<u8>,
+    rules: R,
+    /// Offset in the input of the next unit.
+    position: usize,
+    /// The reactions so far, in trace mode.
+    recorder: Option<Recorder>,
+}
+
+impl Reducer {
+    /// Create a reducer for a new polymer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a reducer for a new polymer, that ignores the units of the given
+    /// type: `unit` (must be within A-Z) and the lowercase version of it.
+    pub fn without(unit: u8) -> Self {
+        Self::with_rules(CaseRules, Some(unit))
+    }
+}
+
+impl<R: ReactionRules> Reducer<R> {
+    /// Create a reducer for a new polymer following the given rules, that
+    /// ignores the units of type `except` (see `ReactionRules::unit_type`), if
+    /// any.
+    pub fn with_rules(rules: R, except: Option<u8>) -> Self {
+        Self {
+            stack: Vec::new(),
+            except,
+            rules,
+            position: 0,
+            recorder: None,
+        }
+    }
+
+    /// Enable the trace mode: every reaction is recorded, see `trace`. This
+    /// uses memory proportional to the input.
+    pub fn with_trace(mut self) -> Self {
+        self.recorder = Some(Recorder::default());
+        self
+    }
+
+    /// The reactions so far and the units left, if the trace mode is enabled.
+    pub fn trace(&self) -> Option<Trace> {
+        self.recorder.as_ref().map(|recorder| {
+            Trace::new(
+                recorder.events.clone(),
+                recorder
+                    .offsets
+                    .iter()
+                    .cloned()
+                    .zip(self.stack.iter().cloned())
+                    .collect(),
+            )
+        })
+    }
+
+    /// Add a unit at the end of the polymer, and let it react. Line feeds and
+    /// ignored units are skipped (but they count for the offsets of the trace).
+    pub fn push(&mut self, unit: u8) {
+        let offset = self.position;
+        self.position += 1;
+        if unit == b'\n' || Some(self.rules.unit_type(unit)) == self.except {
+            return;
+        }
+        let mut unit = unit;
+        loop {
+            let reaction = match self.stack.last() {
+                Some(top) => self.rules.react(*top, unit),
+                None => None,
+            };
+            match reaction {
+                None => {
+                    self.stack.push(unit);
+                    if let Some(recorder) = &mut self.recorder {
+                        recorder.offsets.push(offset);
+                    }
+                    return;
+                }
+                Some(Reaction::Annihilate) => {
+                    self.stack.pop();
+                    if let Some(recorder) = &mut self.recorder {
+                        recorder.react(offset, None);
+                    }
+                    return;
+                }
+                // The product replaces both units, and may react with the
+                // unit before them.
+                Some(Reaction::Transform(product)) => {
+                    self.stack.pop();
+                    if let Some(recorder) = &mut self.recorder {
+                        recorder.react(offset, Some(product));
+                    }
+                    unit = product;
+                }
+            }
+        }
+    }
+
+    /// Add the units at the end of the polymer, and let them react.
+    pub fn extend(&mut self, units: &[u8]) {
+        for unit in units {
+            self.push(*unit);
+        }
+    }
+
+    /// Number of units left in the polymer.
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Return whether every unit reacted.
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// The units left in the polymer.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.stack
+    }
+
+    /// The units left in the polymer, as a string.
+    pub fn into_string(self) -> std::string::String {
+        std::string::String::from_utf8_lossy(&self.stack).into_owned()
+    }
+
+    /// Consume the whole stream, reading it in chunks, and let the units react.
+    pub fn read_from<T: Read>(&mut self, mut reader: T) -> io::Result<()> {
+        let mut buffer = vec![0; CHUNK_SIZE];
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(n) => self.extend(&buffer[..n]),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Reduce the polymer with the rules of the puzzle, recording every reaction.
+pub fn trace_reactions(bytes: &[u8]) -> Trace {
+    let mut reducer = Reducer::new().with_trace();
+    reducer.extend(bytes);
+    reducer.trace().unwrap()
+}
+
+/// Reduce the polymer read from the stream, and return the units left.
+///
+/// The stream is read in chunks, so only the reduced polymer is kept in
+/// memory.
+pub fn reduce_stream<R: Read>(reader: R) -> io::Result<Vec<u8>> {
+    let mut reducer = Reducer::new();
+    reducer.read_from(reader)?;
+    Ok(reducer.stack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reader that returns at most 3 bytes at a time.
+    struct SmallChunks<'a>(&'a [u8]);
+
+    impl<'a> Read for SmallChunks<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(3).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_reduce_stream() {
+        let input = b"dabAcCaCBAcCcaDA\n";
+        assert_eq!(b"dabCBAcaDA".to_vec(), reduce_stream(&input[..]).unwrap());
+        assert_eq!(
+            b"dabCBAcaDA".to_vec(),
+            reduce_stream(SmallChunks(input)).unwrap()
+        );
+        assert!(reduce_stream(SmallChunks(b"abcdDCBA")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_odd_length() {
+        let mut reducer = Reducer::new();
+        reducer.extend(b"aAb");
+        assert_eq!(b"b", reducer.as_bytes());
+        assert_eq!(1, reducer.len());
+    }
+
+    #[test]
+    fn test_transform() {
+        use crate::ReactionTable;
+
+        let table = ReactionTable::from_reader("ab -> c\nbc -> a\nca\n".as_bytes()).unwrap();
+        let mut reducer = Reducer::with_rules(&table, None);
+        // "ab" becomes "c", which reacts with the "b" before it to give "a".
+        reducer.extend(b"bab");
+        assert_eq!(b"a", reducer.as_bytes());
+        // "ca" annihilate, but not "ac".
+        reducer.extend(b"cac");
+        assert_eq!(b"ac", reducer.as_bytes());
+        let mut reducer = Reducer::with_rules(&table, Some(b'b'));
+        reducer.extend(b"bcab");
+        assert!(reducer.is_empty());
+    }
+
+    #[test]
+    fn test_without() {
+        let mut reducer = Reducer::without(b'C');
+        reducer.extend(b"dabAcCaCBAcCcaDA");
+        assert_eq!("daDA".to_string(), reducer.into_string());
+    }
+}
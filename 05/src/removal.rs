@@ -0,0 +1,217 @@
+use std::sync::Mutex;
+use std::thread;
+
+use crate::rules::{CaseRules, ReactionRules};
+use crate::Reducer;
+
+/// Result of the reaction of the polymer without some types of units.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Removal {
+    /// Types of the units removed, sorted.
+    pub units: Vec<u8>,
+    /// Number of units left after the reactions.
+    pub length: usize,
+}
+
+/// Resulting length for each set of removed unit types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemovalReport {
+    /// Length of the polymer after the reactions, without removing anything.
+    pub length: usize,
+    /// One result per set of unit types, by number of types then in
+    /// lexicographic order.
+    pub removals: Vec<Removal>,
+}
+
+impl RemovalReport {
+    /// The removal that leaves the fewest units (the first one, in case of a
+    /// tie).
+    pub fn best(&self) -> Option<&Removal> {
+        self.removals.iter().min_by_key(|r| r.length)
+    }
+}
+
+/// Iterator over every set of 1 to `max_size` elements of `items`, by size then
+/// in lexicographic order of the positions. The sets are built one at a time.
+struct Subsets<'a> {
+    items: &'a [u8],
+    max_size: usize,
+    /// Positions of the items of the next set, always increasing, or None when
+    /// all the sets have been returned.
+    positions: Option<Vec<usize>>,
+}
+
+impl<'a> Subsets<'a> {
+    fn new(items: &'a [u8], max_size: usize) -> Self {
+        let max_size = max_size.min(items.len());
+        Self {
+            items,
+            max_size,
+            positions: if max_size > 0 { Some(vec![0]) } else { None },
+        }
+    }
+}
+
+impl<'a> Iterator for Subsets<'a> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        let items = self.items;
+        let positions = self.positions.as_mut()?;
+        let subset = positions.iter().map(|p| items[*p]).collect();
+        let size = positions.len();
+        // Advance the last position that can still move to the right, and put
+        // the following ones right after it. When none can, move on to the
+        // larger sets.
+        match (0..size).rev().find(|i| positions[*i] < items.len() - size + i) {
+            Some(i) => {
+                positions[i] += 1;
+                for j in i + 1..size {
+                    positions[j] = positions[j - 1] + 1;
+                }
+            }
+            None if size < self.max_size => self.positions = Some((0..=size).collect()),
+            None => self.positions = None,
+        }
+        Some(subset)
+    }
+}
+
+/// Length of the polymer after the reactions, without the units of the given
+/// types.
+fn length_without<R: ReactionRules>(polymer: &[u8], rules: &R, units: &[u8]) -> usize {
+    let mut reducer = Reducer::with_rules(rules, None);
+    for unit in polymer {
+        if !units.contains(&rules.unit_type(*unit)) {
+            reducer.push(*unit);
+        }
+    }
+    reducer.len()
+}
+
+/// Report the length of the polymer after the reactions without the units of
+/// every set of 1 to `max_units` types found in the polymer, with the given
+/// rules. The sets are generated as they are needed by `threads` threads.
+///
+/// When the order of the reactions doesn't matter, as with `CaseRules`, the
+/// polymer can be reduced first: removing units from the reduced polymer gives
+/// the same results, much faster.
+pub fn removal_report_with<R: ReactionRules + Sync>(
+    polymer: &[u8],
+    rules: R,
+    max_units: usize,
+    threads: usize,
+) -> RemovalReport {
+    let mut unit_types: Vec<u8> = polymer
+        .iter()
+        .filter(|u| **u != b'\n')
+        .map(|u| rules.unit_type(*u))
+        .collect();
+    unit_types.sort_unstable();
+    unit_types.dedup();
+    // The threads take the sets one at a time, numbered to restore their order.
+    let candidates = Mutex::new(Subsets::new(&unit_types, max_units).enumerate());
+    let (rules, candidates) = (&rules, &candidates);
+    let mut removals: Vec<(usize, Removal)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads.max(1))
+            .map(|_| {
+                scope.spawn(move || {
+                    let mut removals = Vec::new();
+                    loop {
+                        // Release the lock before the reactions.
+                        let next = candidates.lock().unwrap().next();
+                        match next {
+                            Some((index, units)) => {
+                                let length = length_without(polymer, rules, &units);
+                                removals.push((index, Removal { units, length }));
+                            }
+                            None => return removals,
+                        }
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect()
+    });
+    removals.sort_unstable_by_key(|(index, _)| *index);
+    RemovalReport {
+        length: length_without(polymer, rules, &[]),
+        removals: removals.into_iter().map(|(_, removal)| removal).collect(),
+    }
+}
+
+/// Report the length of the polymer after the reactions without the units of
+/// every set of 1 to `max_units` letters, with the rules of the puzzle. The
+/// input is reduced first, then the sets are tried on as many threads as there
+/// are CPUs.
+///
+/// For example:
+/// ```
+/// let report = lib::removal_report(b"dabAcCaCBAcCcaDA", 1);
+/// assert_eq!(10, report.length);
+/// let lengths: Vec<_> = report.removals.iter().map(|r| (r.units[0], r.length)).collect();
+/// assert_eq!(vec![(b'A', 6), (b'B', 8), (b'C', 4), (b'D', 6)], lengths);
+/// ```
+pub fn removal_report(bytes: &[u8], max_units: usize) -> RemovalReport {
+    let mut reducer = Reducer::new();
+    reducer.extend(bytes);
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    removal_report_with(reducer.as_bytes(), CaseRules, max_units, threads)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subsets() {
+        assert_eq!(
+            vec![
+                b"a".to_vec(),
+                b"b".to_vec(),
+                b"c".to_vec(),
+                b"ab".to_vec(),
+                b"ac".to_vec(),
+                b"bc".to_vec(),
+            ],
+            Subsets::new(b"abc", 2).collect::<Vec<_>>()
+        );
+        assert_eq!(7, Subsets::new(b"abc", 5).count());
+        assert_eq!(0, Subsets::new(b"abc", 0).count());
+        assert_eq!(0, Subsets::new(b"", 2).count());
+        // Only the sets that are used are built.
+        assert_eq!(
+            Some(b"bcd".to_vec()),
+            Subsets::new(b"abcdefghijklmnopqrstuvwxyz", 26).nth(26 + 325 + 300)
+        );
+    }
+
+    #[test]
+    fn test_same_results_from_reduced_polymer() {
+        let input = b"dabAcCaCBAcCcaDA";
+        let mut reducer = Reducer::new();
+        reducer.extend(input);
+        for threads in 1..4 {
+            assert_eq!(
+                removal_report_with(input, CaseRules, 2, threads),
+                removal_report_with(reducer.as_bytes(), CaseRules, 2, threads)
+            );
+        }
+    }
+
+    #[test]
+    fn test_pairs_of_units() {
+        let report = removal_report(b"dabAcCaCBAcCcaDA", 2);
+        assert_eq!(4 + 6, report.removals.len());
+        assert_eq!(
+            Some(&Removal {
+                units: b"AB".to_vec(),
+                length: 0,
+            }),
+            report.best()
+        );
+    }
+}
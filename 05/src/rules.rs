@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+
+use crate::is_pair;
+
+/// What happens when two units are adjacent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reaction {
+    /// Both units are destroyed.
+    Annihilate,
+    /// Both units are replaced by the given unit.
+    Transform(u8),
+}
+
+/// The chemistry of the polymers: which adjacent units react, and how.
+pub trait ReactionRules {
+    /// The reaction between `first` and the unit right after it, `second`, if they react.
+    fn react(&self, first: u8, second: u8) -> Option<Reaction>;
+
+    /// The type of a unit, as used when removing every unit of a type. By default, every unit is
+    /// its own type.
+    fn unit_type(&self, unit: u8) -> u8 {
+        unit
+    }
+}
+
+/// Rules can be shared between several reducers.
+impl<R: ReactionRules + ?Sized> ReactionRules for &R {
+    fn react(&self, first: u8, second: u8) -> Option<Reaction> {
+        (**self).react(first, second)
+    }
+
+    fn unit_type(&self, unit: u8) -> u8 {
+        (**self).unit_type(unit)
+    }
+}
+
+/// The rules of the puzzle: the same letter in both cases annihilate, in any order. The type of
+/// a unit is its uppercase letter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaseRules;
+
+impl ReactionRules for CaseRules {
+    fn react(&self, first: u8, second: u8) -> Option<Reaction> {
+        if is_pair(Some(first), Some(second)) {
+            Some(Reaction::Annihilate)
+        } else {
+            None
+        }
+    }
+
+    fn unit_type(&self, unit: u8) -> u8 {
+        unit.to_ascii_uppercase()
+    }
+}
+
+/// Error returned when loading a reaction table.
+#[derive(Debug)]
+pub enum RulesError {
+    /// The table could not be read.
+    Io(io::Error),
+    /// A line is not a valid reaction.
+    InvalidLine {
+        /// Line of the table where the error was found, starting at 1.
+        line: usize,
+        /// Text of the line.
+        text: String,
+    },
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RulesError::Io(err) => write!(f, "could not read the rules: {}", err),
+            RulesError::InvalidLine { line, text } => {
+                write!(f, "line {}: invalid reaction {:?}", line, text)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RulesError {}
+
+impl From<io::Error> for RulesError {
+    fn from(err: io::Error) -> Self {
+        RulesError::Io(err)
+    }
+}
+
+/// Arbitrary table of reactions between ordered pairs of units.
+///
+/// The table can be loaded from a text file, with one reaction per line:
+/// - `xy` for a unit `x` followed by a unit `y` that annihilate,
+/// - `xy -> z` for a unit `x` followed by a unit `y` that become a unit `z`.
+///
+/// Blank lines and lines starting with `#` are ignored. Pairs are ordered: for units that react
+/// in any order, list both orders.
+///
+/// For example:
+/// ```
+/// use lib::{Reaction, ReactionRules, ReactionTable};
+///
+/// let table = ReactionTable::from_reader("# Comment\naA\nab -> c\n".as_bytes()).unwrap();
+/// assert_eq!(Some(Reaction::Annihilate), table.react(b'a', b'A'));
+/// assert_eq!(None, table.react(b'A', b'a'));
+/// assert_eq!(Some(Reaction::Transform(b'c')), table.react(b'a', b'b'));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReactionTable {
+    reactions: HashMap<(u8, u8), Reaction>,
+}
+
+impl ReactionTable {
+    /// Create a table without any reaction.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the reaction of `first` followed by `second`.
+    pub fn insert(&mut self, first: u8, second: u8, reaction: Reaction) {
+        self.reactions.insert((first, second), reaction);
+    }
+
+    /// Load the table from a reader, in the format described above.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, RulesError> {
+        let mut table = Self::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let text = line.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            let (first, second, reaction) = parse_reaction(text).ok_or_else(|| {
+                RulesError::InvalidLine {
+                    line: index + 1,
+                    text: line.clone(),
+                }
+            })?;
+            table.insert(first, second, reaction);
+        }
+        Ok(table)
+    }
+}
+
+impl ReactionRules for ReactionTable {
+    fn react(&self, first: u8, second: u8) -> Option<Reaction> {
+        self.reactions.get(&(first, second)).cloned()
+    }
+}
+
+/// Parse a unit: a single printable ASCII character.
+fn parse_unit(text: &str) -> Option<u8> {
+    match text.as_bytes() {
+        [unit] if unit.is_ascii_graphic() => Some(*unit),
+        _ => None,
+    }
+}
+
+/// Parse a line of a reaction table, `xy` or `xy -> z`.
+fn parse_reaction(text: &str) -> Option<(u8, u8, Reaction)> {
+    let mut parts = text.splitn(2, "->");
+    let pair = parts.next()?.trim();
+    let reaction = match parts.next() {
+        None => Reaction::Annihilate,
+        Some(product) => Reaction::Transform(parse_unit(product.trim())?),
+    };
+    if !pair.is_char_boundary(1) {
+        return None;
+    }
+    let (first, second) = pair.split_at(1);
+    Some((parse_unit(first)?, parse_unit(second)?, reaction))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_rules() {
+        assert_eq!(Some(Reaction::Annihilate), CaseRules.react(b'a', b'A'));
+        assert_eq!(Some(Reaction::Annihilate), CaseRules.react(b'A', b'a'));
+        assert_eq!(None, CaseRules.react(b'a', b'a'));
+        assert_eq!(b'A', CaseRules.unit_type(b'a'));
+    }
+
+    #[test]
+    fn test_parse_reaction() {
+        assert_eq!(Some((b'a', b'B', Reaction::Annihilate)), parse_reaction("aB"));
+        assert_eq!(
+            Some((b'x', b'y', Reaction::Transform(b'z'))),
+            parse_reaction("xy->z")
+        );
+        assert_eq!(None, parse_reaction("abc"));
+        assert_eq!(None, parse_reaction("a"));
+        assert_eq!(None, parse_reaction("ab -> "));
+        assert_eq!(None, parse_reaction("éa"));
+    }
+
+    #[test]
+    fn test_invalid_table() {
+        match ReactionTable::from_reader("aA\n\nab -> cd\n".as_bytes()) {
+            Err(RulesError::InvalidLine { line, text }) => {
+                assert_eq!(3, line);
+                assert_eq!("ab -> cd", text);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
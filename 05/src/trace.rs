@@ -0,0 +1,181 @@
+use std::fmt::Write;
+
+/// A reaction between two units of the polymer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEvent {
+    /// Order of the reaction, starting at 0.
+    pub step: usize,
+    /// Offset in the input of the unit on the left.
+    pub left: usize,
+    /// Offset in the input of the unit on the right.
+    pub right: usize,
+    /// The unit that replaced both units, if they transformed instead of
+    /// annihilating. The new unit takes the offset of the unit on the right.
+    pub product: Option<u8>,
+}
+
+/// Records the reactions while the reducer runs.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Recorder {
+    /// Offset in the input of each unit of the reducer's stack.
+    pub(crate) offsets: Vec<usize>,
+    pub(crate) events: Vec<TraceEvent>,
+}
+
+impl Recorder {
+    /// Record the reaction of the unit on top of the stack with the unit at
+    /// `right`, and remove it from the stack.
+    pub(crate) fn react(&mut self, right: usize, product: Option<u8>) {
+        let left = self.offsets.pop().unwrap();
+        self.events.push(TraceEvent {
+            step: self.events.len(),
+            left,
+            right,
+            product,
+        });
+    }
+}
+
+/// All the reactions that happened in a polymer, in order, and the units left.
+///
+/// For example:
+/// ```
+/// let trace = lib::trace_reactions(b"dabBAc");
+/// assert_eq!(vec![0, 5], trace.survivors().iter().map(|s| s.0).collect::<Vec<_>>());
+/// // "bB" reacted first, then "aA" around it.
+/// assert_eq!((2, 3), (trace.events()[0].left, trace.events()[0].right));
+/// assert_eq!(vec![1, 0], trace.depths());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    events: Vec<TraceEvent>,
+    survivors: Vec<(usize, u8)>,
+}
+
+impl Trace {
+    pub(crate) fn new(events: Vec<TraceEvent>, survivors: Vec<(usize, u8)>) -> Self {
+        Self { events, survivors }
+    }
+
+    /// The reactions, in the order they happened.
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    /// The units left after the reactions, with their offset in the input.
+    pub fn survivors(&self) -> &[(usize, u8)] {
+        &self.survivors
+    }
+
+    /// The reaction that consumed the unit at the given offset of the input, if
+    /// any.
+    pub fn reaction_of(&self, offset: usize) -> Option<&TraceEvent> {
+        // A product takes the offset of the unit on the right, so it can react
+        // again: the first reaction is the one of the original unit.
+        self.events
+            .iter()
+            .find(|e| e.left == offset || e.right == offset)
+    }
+
+    /// Nesting depth of each reaction, in order: the number of reactions
+    /// around it, between units on both sides of it.
+    pub fn depths(&self) -> Vec<usize> {
+        let mut by_left: Vec<&TraceEvent> = self.events.iter().collect();
+        // Outer reactions first when they start at the same offset.
+        by_left.sort_by_key(|e| (e.left, std::cmp::Reverse(e.right)));
+        let mut depths = vec![0; self.events.len()];
+        // Right offsets of the reactions around the current one.
+        let mut around: Vec<usize> = Vec::new();
+        for event in by_left {
+            while around.last().map(|r| *r < event.left) == Some(true) {
+                around.pop();
+            }
+            depths[event.step] = around.len();
+            around.push(event.right);
+        }
+        depths
+    }
+
+    /// Format the trace as a JSON object, with the reactions (and their depth)
+    /// and the survivors.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"events\":[");
+        for (i, (event, depth)) in self.events.iter().zip(self.depths()).enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write!(
+                json,
+                "{{\"step\":{},\"left\":{},\"right\":{},\"product\":{},\"depth\":{}}}",
+                event.step,
+                event.left,
+                event.right,
+                event.product.map_or("null".to_string(), json_unit),
+                depth
+            )
+            .unwrap();
+        }
+        json.push_str("],\"survivors\":[");
+        for (i, (offset, unit)) in self.survivors.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write!(json, "{{\"offset\":{},\"unit\":{}}}", offset, json_unit(*unit)).unwrap();
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+/// Format a unit as a JSON string.
+fn json_unit(unit: u8) -> String {
+    if unit.is_ascii_graphic() && unit != b'"' && unit != b'\\' {
+        format!("\"{}\"", unit as char)
+    } else {
+        format!("\"\\u{:04x}\"", unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{trace_reactions, Reducer};
+
+    #[test]
+    fn test_trace() {
+        let trace = trace_reactions(b"dabAcCaCBAcCcaDA");
+        let pairs: Vec<_> = trace.events().iter().map(|e| (e.left, e.right)).collect();
+        assert_eq!(vec![(4, 5), (3, 6), (10, 11)], pairs);
+        assert_eq!(vec![1, 0, 0], trace.depths());
+        let survivors: Vec<u8> = trace.survivors().iter().map(|s| s.1).collect();
+        assert_eq!(b"dabCBAcaDA".to_vec(), survivors);
+        assert_eq!(Some(2), trace.reaction_of(11).map(|e| e.step));
+        assert_eq!(None, trace.reaction_of(0));
+    }
+
+    #[test]
+    fn test_depths() {
+        // The "\n" and the ignored units keep their offset.
+        let mut reducer = Reducer::without(b'X').with_trace();
+        reducer.extend(b"abcC\nBxAdD");
+        let trace = reducer.trace().unwrap();
+        let pairs: Vec<_> = trace.events().iter().map(|e| (e.left, e.right)).collect();
+        assert_eq!(vec![(2, 3), (1, 5), (0, 7), (8, 9)], pairs);
+        assert_eq!(vec![2, 1, 0, 0], trace.depths());
+        assert!(trace.survivors().is_empty());
+    }
+
+    #[test]
+    fn test_to_json() {
+        let trace = trace_reactions(b"aAb\"");
+        assert_eq!(
+            "{\"events\":[{\"step\":0,\"left\":0,\"right\":1,\"product\":null,\"depth\":0}],\
+             \"survivors\":[{\"offset\":2,\"unit\":\"b\"},{\"offset\":3,\"unit\":\"\\u0022\"}]}",
+            trace.to_json()
+        );
+    }
+
+    #[test]
+    fn test_no_trace_by_default() {
+        assert_eq!(None, Reducer::new().trace());
+    }
+}
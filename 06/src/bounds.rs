@@ -0,0 +1,133 @@
+use crate::Point;
+
+/// A box of the grid, from `min` to `max` included along each axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Bounds<const N: usize> {
+    pub(crate) min: Point<N>,
+    pub(crate) max: Point<N>,
+}
+
+impl<const N: usize> Bounds<N> {
+    /// The bounding box of the points, extended by `margin` on each side.
+    pub(crate) fn around(points: &[Point<N>], margin: i64) -> Self {
+        let mut min = [0; N];
+        let mut max = [0; N];
+        for axis in 0..N {
+            min[axis] = points.iter().map(|p| p.coordinates[axis]).min().unwrap_or(0) - margin;
+            max[axis] = points.iter().map(|p| p.coordinates[axis]).max().unwrap_or(0) + margin;
+        }
+        Self {
+            min: Point::from(min),
+            max: Point::from(max),
+        }
+    }
+
+    /// Number of locations along each axis.
+    pub(crate) fn sizes(&self) -> [usize; N] {
+        let mut sizes = [0; N];
+        for (axis, size) in sizes.iter_mut().enumerate() {
+            *size = (self.max.coordinates[axis] - self.min.coordinates[axis] + 1).max(0) as usize;
+        }
+        sizes
+    }
+
+    /// Position of the location in `locations()`, if it is in the box.
+    pub(crate) fn index(&self, location: Point<N>) -> Option<usize> {
+        let mut index = 0;
+        let mut stride = 1;
+        for (axis, size) in self.sizes().iter().enumerate() {
+            let offset = location.coordinates[axis] - self.min.coordinates[axis];
+            if offset < 0 || offset as usize >= *size {
+                return None;
+            }
+            index += offset as usize * stride;
+            stride *= size;
+        }
+        Some(index)
+    }
+
+    /// All the locations of the box, along the first axis first: row by row in two dimensions.
+    pub(crate) fn locations(self) -> impl Iterator<Item = Point<N>> {
+        let empty = (0..N).any(|axis| self.min.coordinates[axis] > self.max.coordinates[axis]);
+        let mut next = if empty { None } else { Some(self.min) };
+        std::iter::from_fn(move || {
+            let current = next?;
+            // Increment the first axis, carrying over to the following ones.
+            let mut location = current;
+            next = None;
+            for axis in 0..N {
+                if location.coordinates[axis] < self.max.coordinates[axis] {
+                    location.coordinates[axis] += 1;
+                    next = Some(location);
+                    break;
+                }
+                location.coordinates[axis] = self.min.coordinates[axis];
+            }
+            Some(current)
+        })
+    }
+
+    /// The faces of the box, on each side along each axis: their locations are the locations on
+    /// the edge of the box (with the corners several times).
+    pub(crate) fn faces(self) -> impl Iterator<Item = Bounds<N>> {
+        (0..N).flat_map(move |axis| {
+            let mut first = self;
+            first.max.coordinates[axis] = self.min.coordinates[axis];
+            let mut last = self;
+            last.min.coordinates[axis] = self.max.coordinates[axis];
+            vec![first, last]
+        })
+    }
+}
+
+/// The steps to the neighbors of a location, including the diagonals.
+pub(crate) fn neighbor_steps<const N: usize>() -> Vec<Point<N>> {
+    let unit = Bounds {
+        min: Point::from([-1; N]),
+        max: Point::from([1; N]),
+    };
+    unit.locations()
+        .filter(|step| step.coordinates.iter().any(|c| *c != 0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locations() {
+        let bounds = Bounds::around(&[Point::new(1, 5), Point::new(2, 3)], 0);
+        let locations: Vec<_> = bounds.locations().collect();
+        assert_eq!(
+            vec![(1, 3), (2, 3), (1, 4), (2, 4), (1, 5), (2, 5)]
+                .into_iter()
+                .map(Point::from)
+                .collect::<Vec<_>>(),
+            locations
+        );
+        for (index, location) in locations.iter().enumerate() {
+            assert_eq!(Some(index), bounds.index(*location));
+        }
+        assert_eq!(None, bounds.index(Point::new(0, 3)));
+        assert_eq!(4, bounds.faces().count());
+        assert_eq!(26, neighbor_steps::<3>().len());
+    }
+
+    #[test]
+    fn test_extreme_locations() {
+        let bounds = Bounds {
+            min: Point::new(i64::MAX - 1, i64::MIN),
+            max: Point::new(i64::MAX, i64::MIN),
+        };
+        assert_eq!(
+            vec![Point::new(i64::MAX - 1, i64::MIN), Point::new(i64::MAX, i64::MIN)],
+            bounds.locations().collect::<Vec<_>>()
+        );
+        let line = Bounds {
+            min: Point::new(i64::MIN, 0),
+            max: Point::new(i64::MAX, 0),
+        };
+        assert_eq!(Some(Point::new(i64::MIN, 0)), line.locations().next());
+    }
+}
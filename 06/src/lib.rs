@@ -1,251 +1,357 @@
 #[macro_use]
 extern crate nom;
-#[macro_use]
-extern crate derive_more;
-
-extern crate itertools;
 
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{Add, Sub};
 use std::str::FromStr;
 use std::string::String;
 use nom::digit;
 use nom::types::CompleteStr;
-use itertools::{iterate, Itertools};
-use itertools::MinMaxResult::MinMax;
 
-#[derive(Eq, PartialEq, Debug, Copy, Clone, Add, Sub, Display, Ord, PartialOrd, From, Into, Mul)]
-struct Distance(i32);
+mod bounds;
+mod metric;
+mod safe_region;
+mod voronoi;
+
+pub use crate::metric::{Chebyshev, Manhattan, Metric, SquaredEuclidean};
+pub use crate::voronoi::{Label, VoronoiMap};
 
-#[derive(Eq, PartialEq, Debug, Copy, Clone, Add, Sub, Display, Constructor, From, Into)]
-#[display(fmt = "{}, {}", "x", "y")]
-struct Point {
-    x: i32,
-    y: i32,
+/// A location on the grid, in `N` dimensions (2 by default).
+///
+/// The points of the input have coordinates that fit in 32 bits: they are stored on 64 bits, so
+/// that the locations around them and the differences between them can't overflow.
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Hash)]
+pub struct Point<const N: usize = 2> {
+    pub coordinates: [i64; N],
 }
 
 impl Point {
-    fn dist(self, other: Self) -> Distance {
-        Distance((self.x - other.x).abs() + (self.y - other.y).abs())
+    /// Create a point in two dimensions.
+    pub fn new(x: i64, y: i64) -> Self {
+        Self {
+            coordinates: [x, y],
+        }
+    }
+
+    /// First coordinate of a point in two dimensions.
+    pub fn x(&self) -> i64 {
+        self.coordinates[0]
+    }
+
+    /// Second coordinate of a point in two dimensions.
+    pub fn y(&self) -> i64 {
+        self.coordinates[1]
     }
 }
 
-/// The 4 cardinal directions, in a random order.
-const DIRECTIONS: [Point; 4] = [
-    Point { x: 0, y: 1 },
-    Point { x: 0, y: -1 },
-    Point { x: 1, y: 0 },
-    Point { x: -1, y: 0 },
-];
+impl<const N: usize> From<[i64; N]> for Point<N> {
+    fn from(coordinates: [i64; N]) -> Self {
+        Self { coordinates }
+    }
+}
+
+impl From<(i64, i64)> for Point {
+    fn from((x, y): (i64, i64)) -> Self {
+        Self::new(x, y)
+    }
+}
 
-/// Parse an int.
-named!(i32 <&str, i32>,
-   map!(map!(digit, FromStr::from_str), Result::unwrap)
+impl<const N: usize> Add for Point<N> {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        for (c, o) in self.coordinates.iter_mut().zip(other.coordinates.iter()) {
+            *c += o;
+        }
+        self
+    }
+}
+
+impl<const N: usize> Sub for Point<N> {
+    type Output = Self;
+
+    fn sub(mut self, other: Self) -> Self {
+        for (c, o) in self.coordinates.iter_mut().zip(other.coordinates.iter()) {
+            *c -= o;
+        }
+        self
+    }
+}
+
+impl<const N: usize> fmt::Display for Point<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, c) in self.coordinates.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+// Parse a signed int.
+named!(i32 <CompleteStr, i32>,
+   map_res!(recognize!(pair!(opt!(tag!("-")), digit)), |s: CompleteStr| FromStr::from_str(s.0))
 );
 
-/// Parse a point.
-named!(point <&str, Point>,
-   do_parse!(
-       p: separated_pair!(i32, tag!(", "), i32) >>
-       (Point::from(p))
-));
+// Parse the coordinates of a point, in any dimension.
+named!(coordinates <CompleteStr, Vec<i32>>,
+   separated_nonempty_list!(tag!(", "), i32)
+);
 
 
-/// Convenience function to parse a point.
-fn parse_point(input: &str) -> Point {
-    point(&CompleteStr(input)).unwrap().1
+/// Error returned when parsing the points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointsError {
+    /// A line is not a valid point, or it doesn't have the expected number of coordinates.
+    InvalidLine {
+        /// Line of the input where the error was found, starting at 1.
+        line: usize,
+        /// Text of the line.
+        text: String,
+    },
+    /// A point appears twice: no location would be closer to it than to its copy.
+    Duplicate {
+        /// Line of the second occurrence, starting at 1.
+        line: usize,
+        /// Line of the first occurrence.
+        first: usize,
+        /// Text of the line.
+        text: String,
+    },
 }
 
-/// Checks if the point at (`origin_x`, `origin_y`) is the closest one to each of the points on the
-/// half-ray going in the positive x position, or if (`point_x`, `point_y`) is going to be closer to a
-/// point (at some point).
-fn is_point_blocking_direction(point_x: i32, origin_x: i32, point_y: i32, origin_y: i32) -> bool {
-    if origin_x >= point_x {
-        return false;
+impl fmt::Display for PointsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PointsError::InvalidLine { line, text } => {
+                write!(f, "line {}: invalid point {:?}", line, text)
+            }
+            PointsError::Duplicate { line, first, text } => write!(
+                f,
+                "line {}: point {:?} already given on line {}",
+                line, text, first
+            ),
+        }
     }
-    (point_x - origin_x) >= (point_y - origin_y).abs()
 }
 
-/// Checks if `origin` is the closest point to the half-ray in direction `direction` or if `point` is
-/// going to be closer to some of the points.
-fn is_point_blocking(point: Point, origin: Point, direction: Point) -> bool {
-    if direction.x == 0 {
-        if direction.y > 0 {
-            is_point_blocking_direction(point.y, origin.y, point.x, origin.x)
-        } else {
-            is_point_blocking_direction(origin.y, point.y, origin.x, point.x)
+impl std::error::Error for PointsError {}
+
+/// Convenience function to parse a point in `N` dimensions, if the line is valid.
+fn parse_point<const N: usize>(input: &str) -> Option<Point<N>> {
+    match coordinates(CompleteStr(input.trim())) {
+        Ok((rest, coordinates)) if rest.is_empty() => {
+            let coordinates = <[i32; N]>::try_from(coordinates.as_slice()).ok()?;
+            Some(Point::from(coordinates.map(i64::from)))
         }
-    } else if direction.x > 0 {
-        is_point_blocking_direction(point.x, origin.x, point.y, origin.y)
-    } else {
-        is_point_blocking_direction(origin.x, point.x, origin.y, point.y)
-    }
-}
-
-/// Checks if `origin` is the closest point to the half-ray in direction `direction` or if there is a
-/// point that is going to be closer to some of the points.
-fn is_infinite_direction(
-    origin: Point,
-    dist_from_origin: &[(&Point, Distance)],
-    direction: Point,
-) -> bool {
-    !dist_from_origin.iter().any(|(&p, _)| {
-        is_point_blocking(p, origin, direction)
-    })
-}
-
-/// Check if `origin` has an unbounded area where it is the closest point, by checking the 4 axis.
-/// This is enough, because the distance is the Manhattan distance.
-fn is_infinite_point(origin: Point, dist_from_origin: &[(&Point, Distance)]) -> bool {
-    DIRECTIONS.iter().any(|d| {
-        is_infinite_direction(origin, &dist_from_origin, *d)
-    })
-}
-
-/// Checks whether `origin` is the closest point to `point`.
-fn is_closest_point(
-    point: Point,
-    origin: Point,
-    dist_from_origin: &[(&Point, Distance)],
-) -> bool {
-    let mut point_distances = dist_from_origin
-        .iter()
-        .take_while(|(_, d)| *d <= point.dist(origin) * 2)
-        .map(|(p, _)| p.dist(point))
-        .collect::<Vec<_>>();
-    point_distances.sort();
-    point_distances[0] == origin.dist(point) &&
-        (point_distances.len() == 1 || point_distances[1] > point_distances[0])
-}
-
-/// Find the distance to the furthest point along a half-ray that is still closer to `origin` than to
-/// any other point.
-fn find_furthest_point(
-    origin: Point,
-    dist_from_origin: &[(&Point, Distance)],
-    direction: Point,
-) -> Distance {
-    iterate(origin, |&p| p + direction)
-        .take_while(|p| is_closest_point(*p, origin, dist_from_origin))
-        .last()
-        .unwrap()
-        .dist(origin)
-}
-
-/// Get the distance of each of the `points` to `origin`.
-fn get_dist_from_origin(points: &[Point], origin: Point) -> Vec<(&Point, Distance)> {
-    let mut dist_from_origin = points.iter().map(|p| (p, p.dist(origin))).collect::<Vec<_>>();
-    dist_from_origin.sort_by_key(|(_, dist)| *dist);
-    dist_from_origin
-}
-
-/// Find the area of the points that are closer to `origin` than to any other `points`. If the area is
-/// unbounded, return None.
-fn find_close_area(points: &[Point], origin: Point) -> Option<usize> {
-    let dist_from_origin = get_dist_from_origin(points, origin);
-    if is_infinite_point(origin, &dist_from_origin) {
-        return None;
-    }
-    // Edges of the rectangle in which all the points are contained. These are distances in the
-    // corresponding DIRECTIONS.
-    let edges: Vec<_> = DIRECTIONS
-        .iter()
-        .map(|d| find_furthest_point(origin, &dist_from_origin, *d))
-        .collect();
-    Some(
-        ((origin.x - i32::from(edges[3]))..=(origin.x + i32::from(edges[2])))
-            .cartesian_product(
-                (origin.y - i32::from(edges[1]))..=(origin.y + i32::from(edges[0])),
-            )
-            .map(|(x, y)| Point { x, y })
-            .filter(|&p| is_closest_point(p, origin, &dist_from_origin))
-            .count(),
-    )
-}
-
-/// Find the point with the largest area in which it is the closest point from the list `lines`.
-pub fn find_largest_close_area(lines: &[String]) -> usize {
-    let points = lines.iter().map(|s| parse_point(s)).collect::<Vec<_>>();
-    points
+        _ => None,
+    }
+}
+
+/// Parse the points in `N` dimensions, one per line. The blank lines are skipped.
+///
+/// For example:
+/// ```
+/// use lib::{Point, PointsError};
+///
+/// let lines = vec!["-1, 2".to_string(), "3, -4".to_string()];
+/// assert_eq!(Ok(vec![Point::new(-1, 2), Point::new(3, -4)]), lib::parse_points(&lines));
+/// let lines = vec!["1, 2".to_string(), "3, 4, 5".to_string()];
+/// assert_eq!(
+///     Err(PointsError::InvalidLine { line: 2, text: "3, 4, 5".to_string() }),
+///     lib::parse_points::<2>(&lines)
+/// );
+/// ```
+pub fn parse_points<const N: usize>(lines: &[String]) -> Result<Vec<Point<N>>, PointsError> {
+    let mut points = Vec::with_capacity(lines.len());
+    // Line of each point, to report the duplicates.
+    let mut first_lines = HashMap::new();
+    for (index, text) in lines.iter().enumerate() {
+        if text.trim().is_empty() {
+            continue;
+        }
+        let point = parse_point(text).ok_or_else(|| PointsError::InvalidLine {
+            line: index + 1,
+            text: text.clone(),
+        })?;
+        if let Some(first) = first_lines.get(&point) {
+            return Err(PointsError::Duplicate {
+                line: index + 1,
+                first: *first,
+                text: text.clone(),
+            });
+        }
+        first_lines.insert(point, index + 1);
+        points.push(point);
+    }
+    Ok(points)
+}
+
+/// The number of coordinates of the first point of the list `lines`, if any.
+pub fn dimensions(lines: &[String]) -> Option<usize> {
+    lines
         .iter()
-        .filter_map(|&p| find_close_area(&points, p))
-        .max()
-        .unwrap()
+        .find(|line| !line.trim().is_empty())
+        .and_then(|line| coordinates(CompleteStr(line.trim())).ok())
+        .map(|(_, coordinates)| coordinates.len())
 }
 
-/// Check that the sum of the distances of all the pointst to `location` is under `max_distance`.
-fn all_points_within(max_distance: Distance, location: Point, points: &[Point]) -> bool {
-    points.iter().map(|&p| location.dist(p)).fold(
-        Distance(0),
-        std::ops::Add::add,
-    ) <= max_distance
+/// Compute the Voronoi map of the points from the list `lines` with the given metric, with
+/// `margin` locations around their bounding box.
+pub fn build_map<M: Metric>(
+    lines: &[String],
+    margin: i32,
+    metric: M,
+) -> Result<VoronoiMap, PointsError> {
+    Ok(VoronoiMap::with_metric(&parse_points(lines)?, margin, metric))
 }
 
-/// Get the min and the max from an iterator (assumed to have at least 2 different elements).
-fn get_min_max<I>(iter: I) -> (i32, i32)
-where
-    I: Iterator<Item = i32>,
-{
-    match iter.minmax() {
-        MinMax(min, max) => (min, max),
-        _ => panic!("Not enough points!"),
-    }
+/// Find the point with the largest finite area in which it is the closest point from the list
+/// `lines`, in `N` dimensions, with the given metric.
+pub fn find_largest_close_area<const N: usize, M: Metric<N>>(
+    lines: &[String],
+    metric: M,
+) -> Result<usize, PointsError> {
+    Ok(metric
+        .finite_region_sizes(&parse_points(lines)?)
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or(0))
 }
 
 /// Find the area of the points for which the sum of the distances to each of the locations given
-/// in `lines` are under `max_distance`.
-pub fn find_area_close_to_points(lines: &[String], max_distance: i32) -> usize {
-    let points = lines.iter().map(|s| parse_point(s)).collect::<Vec<_>>();
-    let (min_x, max_x) = get_min_max(points.iter().map(|p| p.x));
-    let (min_y, max_y) = get_min_max(points.iter().map(|p| p.y));
-    (min_x..=max_x)
-        .cartesian_product(min_y..=max_y)
-        .map(Point::from)
-        .filter(|&p| all_points_within(Distance(max_distance), p, &points))
-        .count()
+/// in `lines` is at most `max_distance`, in `N` dimensions, with the given metric.
+///
+/// The area can extend beyond the bounding box of the locations, it is counted exactly.
+pub fn find_area_close_to_points<const N: usize, M: Metric<N>>(
+    lines: &[String],
+    max_distance: i32,
+    metric: M,
+) -> Result<usize, PointsError> {
+    Ok(metric.count_within_distance(&parse_points(lines)?, max_distance))
 }
 
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+
+    /// The example of the puzzle.
+    const EXAMPLE: [&str; 6] = ["1, 1", "1, 6", "8, 3", "3, 4", "5, 5", "8, 9"];
+
+    /// The input lines with the given text.
+    pub(crate) fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|l| l.to_string()).collect()
+    }
+
+    /// Points in two dimensions, from their coordinates.
+    pub(crate) fn points(coordinates: &[(i64, i64)]) -> Vec<Point> {
+        coordinates.iter().map(|&p| Point::from(p)).collect()
+    }
+
+    /// The points of the example of the puzzle.
+    pub(crate) fn example_points() -> Vec<Point> {
+        parse_points(&lines(&EXAMPLE)).unwrap()
+    }
+
     #[test]
     fn test_parse_point() {
-        assert_eq!(parse_point(&"1, 32".to_string()), Point { x: 1, y: 32 });
+        assert_eq!(parse_point("1, 32"), Some(Point::new(1, 32)));
+        assert_eq!(parse_point("1, 32, 7"), Some(Point::from([1, 32, 7])));
+        assert_eq!(parse_point(" -1, -32 "), Some(Point::new(-1, -32)));
+        assert_eq!(None, parse_point::<2>("1, 32, 7"));
+        assert_eq!(None, parse_point::<2>("1, 3x"));
+        assert_eq!(None, parse_point::<2>("1, --3"));
+        assert_eq!(None, parse_point::<2>("1, 3000000000"));
+        assert_eq!(Some(3), dimensions(&["1, 32, 7".to_string()]));
+        assert_eq!(None, dimensions(&[]));
+    }
+
+    #[test]
+    fn test_parse_points_errors() {
+        let lines = lines(&["1, 2", "", "3, 4", "1, 2"]);
+        assert_eq!(
+            Err(PointsError::Duplicate {
+                line: 4,
+                first: 1,
+                text: "1, 2".to_string()
+            }),
+            parse_points::<2>(&lines)
+        );
+        assert_eq!(
+            "line 4: point \"1, 2\" already given on line 1",
+            parse_points::<2>(&lines).unwrap_err().to_string()
+        );
+        let lines = self::lines(&["1, 2", "a, b"]);
+        assert_eq!(
+            Err(PointsError::InvalidLine {
+                line: 2,
+                text: "a, b".to_string()
+            }),
+            find_largest_close_area::<2, _>(&lines, Manhattan)
+        );
+    }
+
+    #[test]
+    fn test_negative_coordinates() {
+        // The example of the puzzle, centred on the origin.
+        let lines = lines(&["-4, -4", "-4, 1", "3, -2", "-2, -1", "0, 0", "3, 4"]);
+        assert_eq!(Ok(17), find_largest_close_area::<2, _>(&lines, Manhattan));
+        assert_eq!(Ok(16), find_area_close_to_points::<2, _>(&lines, 31, Manhattan));
     }
 
-    /// Test util to check the is_infinite_direction function.
-    fn test_infinite_direction(origin: Point, other_points: Vec<Point>, expected_blocks: &[usize]) {
-        let mut points = other_points.clone();
-        points.push(origin);
-        let dist_from_origin = get_dist_from_origin(&points, origin);
-        for (i, d) in DIRECTIONS.iter().enumerate() {
-            let expected = expected_blocks.iter().all(|&e| e != i);
+    #[test]
+    fn test_extreme_coordinates() {
+        let lines = lines(&["2147483647, -2147483648", "-2147483648, 2147483647"]);
+        assert_eq!(
+            Ok(vec![
+                Point::new(i64::from(i32::MAX), i64::from(i32::MIN)),
+                Point::new(i64::from(i32::MIN), i64::from(i32::MAX)),
+            ]),
+            parse_points(&lines)
+        );
+        assert_eq!(None, parse_point::<2>("2147483648, 0"));
+        // The example of the puzzle, moved to the corners of the coordinates.
+        let (min, max) = (i64::from(i32::MIN), i64::from(i32::MAX));
+        for (dx, dy) in &[(max - 9, max - 9), (min, min), (min, max - 9)] {
+            let lines = example_points()
+                .iter()
+                .map(|p| format!("{}, {}", p.x() + dx, p.y() + dy))
+                .collect::<Vec<_>>();
+            assert_eq!(Ok(17), find_largest_close_area::<2, _>(&lines, Manhattan));
+            assert_eq!(Ok(16), find_area_close_to_points::<2, _>(&lines, 31, Manhattan));
+            assert_eq!(Ok(16), find_largest_close_area::<2, _>(&lines, SquaredEuclidean));
             assert_eq!(
-                is_infinite_direction(origin, &dist_from_origin, *d),
-                expected,
-                "Expected direction ({}) from ({}) with other points {:?} to be {}, got {}",
-                d,
-                origin,
-                other_points,
-                expected,
-                !expected
+                find_largest_close_area::<2, _>(&self::lines(&EXAMPLE), Chebyshev),
+                find_largest_close_area::<2, _>(&lines, Chebyshev)
             );
         }
+        // Points spread across the whole range: all the regions are infinite.
+        let lines = self::lines(&["-2147483648, 0", "2147483647, 0", "0, 5", "1, -3"]);
+        assert_eq!(Ok(0), find_largest_close_area::<2, _>(&lines, Manhattan));
+        assert_eq!(Ok(0), find_largest_close_area::<2, _>(&lines, Chebyshev));
     }
 
     #[test]
-    fn test_is_infinite_direction() {
-        test_infinite_direction(Point::new(5, 5), vec![Point::new(0, 1)], &[3]);
-        test_infinite_direction(Point::new(5, 5), vec![Point::new(10, 0)], &[1, 2]);
-        test_infinite_direction(Point::new(5, 5), vec![Point::new(1, 0)], &[1]);
-        test_infinite_direction(
-            Point::new(5, 5),
-            vec![
-                Point::new(1, 0),
-                Point::new(0, 6),
-                Point::new(10, 6),
-                Point::new(6, 10),
-            ],
-            &[0, 1, 2, 3],
-        );
+    fn test_find_largest_close_area() {
+        let lines = lines(&EXAMPLE);
+        assert_eq!(Ok(17), find_largest_close_area::<2, _>(&lines, Manhattan));
+        assert_eq!(Ok(16), find_area_close_to_points::<2, _>(&lines, 31, Manhattan));
+        assert_eq!(Ok(16), find_largest_close_area(&lines, &SquaredEuclidean as &dyn Metric));
+    }
+
+    #[test]
+    fn test_three_dimensions() {
+        // A point surrounded by six others, one on each side.
+        let lines = lines(&[
+            "3, 3, 3", "0, 3, 3", "6, 3, 3", "3, 0, 3", "3, 6, 3", "3, 3, 0", "3, 3, 6",
+        ]);
+        assert_eq!(Ok(27), find_largest_close_area::<3, _>(&lines, Manhattan));
+        assert_eq!(Ok(129), find_area_close_to_points::<3, _>(&lines, 40, Manhattan));
     }
 }
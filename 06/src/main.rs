@@ -1,18 +1,91 @@
 extern crate lib;
+use std::fs::File;
 use std::io;
-use std::io::BufRead;
+use std::io::{BufRead, BufWriter};
 use std::string::String;
 
-fn main() {
-    let stdin = io::stdin();
-    // Read the lines from stdin.
-    let lines: Vec<String> = stdin.lock().lines().map(Result::unwrap).collect();
+use lib::{Chebyshev, Manhattan, Metric, PointsError, SquaredEuclidean};
+
+/// Margin around the points in the rendered map, to show the infinite regions.
+const IMAGE_MARGIN: i32 = 20;
+
+/// Metric with the given name, in `N` dimensions.
+fn metric<const N: usize>(name: &str) -> &'static dyn Metric<N> {
+    match name {
+        "manhattan" => &Manhattan,
+        "chebyshev" => &Chebyshev,
+        "euclidean" => &SquaredEuclidean,
+        _ => {
+            println!("Unknown metric: {}", name);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Print the answers for points in `N` dimensions.
+fn print_areas<const N: usize>(lines: &[String], metric_name: &str) -> Result<(), PointsError> {
+    let metric = metric::<N>(metric_name);
     println!(
         "Largest area: {}",
-        lib::find_largest_close_area(lines.as_slice())
+        lib::find_largest_close_area(lines, metric)?
     );
     println!(
         "Largest safe area: {}",
-        lib::find_area_close_to_points(lines.as_slice(), 10000)
+        lib::find_area_close_to_points(lines, 10000, metric)?
     );
+    Ok(())
+}
+
+/// Usage: day_06 [--metric manhattan|chebyshev|euclidean] [--output <image.ppm>] < input
+///
+/// The points can have 2 to 4 coordinates, all the points of the input must have the same number.
+/// The distance is Manhattan's by default. With `--output`, the map of the regions closest to each
+/// point (in two dimensions) is also rendered as a color image.
+fn main() {
+    let mut metric_name = "manhattan".to_string();
+    let mut output = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--metric", Some(name)) => metric_name = name,
+            ("--output", Some(path)) => output = Some(path),
+            _ => {
+                println!(
+                    "Usage: day_06 [--metric manhattan|chebyshev|euclidean] \
+                     [--output <image.ppm>] < input"
+                );
+                std::process::exit(2);
+            }
+        }
+    }
+    let stdin = io::stdin();
+    // Read the lines from stdin.
+    let lines: Vec<String> = stdin.lock().lines().map(Result::unwrap).collect();
+    let dimensions = lib::dimensions(&lines).unwrap_or(2);
+    let result = match dimensions {
+        2 => print_areas::<2>(&lines, &metric_name),
+        3 => print_areas::<3>(&lines, &metric_name),
+        4 => print_areas::<4>(&lines, &metric_name),
+        _ => {
+            println!("Unsupported number of dimensions: {}", dimensions);
+            std::process::exit(2);
+        }
+    };
+    if let Err(err) = result {
+        println!("Error reading the points: {}", err);
+        std::process::exit(2);
+    }
+    if let Some(path) = output {
+        if dimensions != 2 {
+            println!("Only maps in two dimensions can be rendered");
+            std::process::exit(2);
+        }
+        // The points were already checked.
+        let map = lib::build_map(&lines, IMAGE_MARGIN, metric::<2>(&metric_name)).unwrap();
+        let file = BufWriter::new(File::create(&path).expect("Could not create output file"));
+        if let Err(err) = map.write_ppm(file) {
+            println!("Error writing image: {}", err);
+            std::process::exit(2);
+        }
+    }
 }
@@ -0,0 +1,629 @@
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use crate::bounds::{neighbor_steps, Bounds};
+use crate::safe_region;
+use crate::voronoi::{closest_point, Label};
+use crate::Point;
+
+/// A distance between the locations of the grid in `N` dimensions, used to find the closest
+/// points.
+///
+/// The computation of the safe region expects the distance to be at least the difference along
+/// each axis, and to be convex along each axis.
+pub trait Metric<const N: usize = 2> {
+    /// Distance between two locations. It is wide enough for the squares of the differences.
+    fn distance(&self, a: Point<N>, b: Point<N>) -> i128;
+
+    /// Whether the region of the locations strictly closest to each point is infinite.
+    fn infinite_regions(&self, points: &[Point<N>]) -> Vec<bool>;
+
+    /// Number of locations strictly closest to the point with the given index, whose region is
+    /// finite.
+    ///
+    /// By default, the region is explored from the point, with steps along the axes and the
+    /// diagonals. This finds the whole region when moving a location one step towards the point
+    /// along every axis keeps it strictly closest to the point: the distance to the point
+    /// decreases at least as much as the others.
+    fn finite_region_size(&self, points: &[Point<N>], index: usize) -> usize {
+        explore_region(self, points, index)
+    }
+
+    /// Size of the region of each point, or None if it is infinite.
+    fn finite_region_sizes(&self, points: &[Point<N>]) -> Vec<Option<usize>> {
+        self.infinite_regions(points)
+            .into_iter()
+            .enumerate()
+            .map(|(index, infinite)| {
+                if infinite {
+                    None
+                } else {
+                    Some(self.finite_region_size(points, index))
+                }
+            })
+            .collect()
+    }
+
+    /// Count the locations whose sum of distances to all the `points` is at most
+    /// `max_distance`, wherever they are. Like the coordinates of the points, the distance fits in
+    /// 32 bits, so the locations are within 64 bits.
+    fn count_within_distance(&self, points: &[Point<N>], max_distance: i32) -> usize {
+        safe_region::count_within_distance(self, points, max_distance)
+    }
+}
+
+/// Metrics can be shared, or chosen at runtime.
+impl<const N: usize, M: Metric<N> + ?Sized> Metric<N> for &M {
+    fn distance(&self, a: Point<N>, b: Point<N>) -> i128 {
+        (**self).distance(a, b)
+    }
+
+    fn infinite_regions(&self, points: &[Point<N>]) -> Vec<bool> {
+        (**self).infinite_regions(points)
+    }
+
+    fn finite_region_size(&self, points: &[Point<N>], index: usize) -> usize {
+        (**self).finite_region_size(points, index)
+    }
+
+    fn finite_region_sizes(&self, points: &[Point<N>]) -> Vec<Option<usize>> {
+        (**self).finite_region_sizes(points)
+    }
+
+    fn count_within_distance(&self, points: &[Point<N>], max_distance: i32) -> usize {
+        (**self).count_within_distance(points, max_distance)
+    }
+}
+
+/// The distance of the puzzle: the sum of the differences along each axis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Manhattan;
+
+impl<const N: usize> Metric<N> for Manhattan {
+    fn distance(&self, a: Point<N>, b: Point<N>) -> i128 {
+        difference(a, b).map(i128::abs).sum()
+    }
+
+    /// Moving a location on the edge of the bounding box away from it increases its distance to
+    /// all the points by one, since they are all inside: its label stays the same. So the regions
+    /// that touch the edge extend forever, and the other regions are inside the box.
+    ///
+    /// If another point is at least as close as `p` to the location where `p` is moved straight
+    /// to a face of the box, it is at least as close to every location of the face, by the
+    /// triangle inequality within the face. So the region of `p` is infinite exactly when `p`
+    /// stays the closest point while moving away from it along an axis.
+    fn infinite_regions(&self, points: &[Point<N>]) -> Vec<bool> {
+        escaping_regions(points, |step, difference| {
+            step.coordinates
+                .iter()
+                .zip(difference)
+                .map(|(s, d)| if *s == 0 { d.abs() } else { i128::from(*s) * d })
+                .sum()
+        })
+    }
+
+    /// The distance is separable, the sums are computed along each axis.
+    fn count_within_distance(&self, points: &[Point<N>], max_distance: i32) -> usize {
+        safe_region::count_within_manhattan_distance(points, max_distance)
+    }
+}
+
+/// The largest of the differences along each axis, the number of moves of a king.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Chebyshev;
+
+impl<const N: usize> Metric<N> for Chebyshev {
+    fn distance(&self, a: Point<N>, b: Point<N>) -> i128 {
+        difference(a, b).map(i128::abs).max().unwrap_or(0)
+    }
+
+    /// Far from the points along the diagonal ray `p + t * s` (with `s` made of +1 and -1), the
+    /// distance to a point `q` is `t + max(s_i * (p_i - q_i))`. If another point `q` is at least
+    /// as close as `p` along every diagonal ray from `p`, then `q` is beyond `p` along every axis
+    /// in some diagonal direction, and it is at least as close as `p` to every location far in
+    /// that direction. So the region of `p` is infinite exactly when `p` stays the closest point
+    /// along one of the rays.
+    fn infinite_regions(&self, points: &[Point<N>]) -> Vec<bool> {
+        escaping_regions(points, |step, difference| {
+            step.coordinates
+                .iter()
+                .zip(difference)
+                .filter(|(s, _)| **s != 0)
+                .map(|(s, d)| i128::from(*s) * d)
+                .max()
+                .unwrap_or(0)
+        })
+    }
+}
+
+/// The square of the Euclidean distance, which keeps integer distances and gives the same
+/// closest points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SquaredEuclidean;
+
+impl<const N: usize> Metric<N> for SquaredEuclidean {
+    fn distance(&self, a: Point<N>, b: Point<N>) -> i128 {
+        difference(a, b).map(|d| d * d).sum()
+    }
+
+    /// The regions are convex. A point on the boundary of the convex hull of the points is the
+    /// closest one along the outward normal of a face of the hull through it, forever. A point
+    /// strictly inside the hull is surrounded by other points, its region is bounded.
+    ///
+    /// The faces of the hull are found among the hyperplanes through `N` points that have all the
+    /// points on the same side. When there are none, all the points are in a smaller subspace,
+    /// and they are all on the boundary.
+    fn infinite_regions(&self, points: &[Point<N>]) -> Vec<bool> {
+        let duplicated = duplicated_points(points);
+        let mut on_boundary = vec![false; points.len()];
+        let mut flat = true;
+        for subset in combinations(points.len(), N) {
+            let base = points[subset[0]];
+            let normal = normal(&subset.iter().map(|i| points[*i] - base).collect::<Vec<_>>());
+            if normal.iter().all(|c| *c == 0) {
+                continue;
+            }
+            flat = false;
+            let sides: Vec<i128> = points.iter().map(|p| dot(&normal, &wide(*p - base))).collect();
+            if sides.iter().all(|s| *s <= 0) || sides.iter().all(|s| *s >= 0) {
+                for (index, side) in sides.iter().enumerate() {
+                    if *side == 0 {
+                        on_boundary[index] = true;
+                    }
+                }
+            }
+        }
+        duplicated
+            .into_iter()
+            .zip(on_boundary)
+            .map(|(duplicated, on_boundary)| !duplicated && (flat || on_boundary))
+            .collect()
+    }
+
+    /// The region is the convex polytope of the locations `l` where, for each other point `q`:
+    /// `2 <l, q - p> <= |q|^2 - |p|^2`. Its corners are where `N` of these constraints are tight:
+    /// the locations of the region, where they are all strict, are counted line by line in the
+    /// bounding box of the corners.
+    ///
+    /// A region can go far beyond the points when they are almost aligned, and it can be too thin
+    /// to be explored one step at a time.
+    fn finite_region_size(&self, points: &[Point<N>], index: usize) -> usize {
+        let point = points[index];
+        let squared_norm = |p: Point<N>| wide(p).iter().map(|c| c * c).sum::<i128>();
+        let constraints: Vec<(Vec<i128>, i128)> = points
+            .iter()
+            .filter(|q| **q != point)
+            .map(|q| {
+                let normal = wide(*q - point).into_iter().map(|c| 2 * c).collect();
+                (normal, squared_norm(*q) - squared_norm(point))
+            })
+            .collect();
+        if constraints.len() + 1 < points.len() {
+            // The point is duplicated, no location is strictly closest to it.
+            return 0;
+        }
+        let mut min = [i64::MAX; N];
+        let mut max = [i64::MIN; N];
+        for subset in combinations(constraints.len(), N) {
+            let matrix: Vec<Vec<i128>> = subset.iter().map(|i| constraints[*i].0.clone()).collect();
+            let mut denominator = determinant(matrix.clone());
+            if denominator == 0 {
+                continue;
+            }
+            // Cramer's rule: the corner is `numerators / denominator`.
+            let mut numerators: Vec<i128> = (0..N)
+                .map(|axis| {
+                    let mut replaced = matrix.clone();
+                    for (row, i) in replaced.iter_mut().zip(&subset) {
+                        row[axis] = constraints[*i].1;
+                    }
+                    determinant(replaced)
+                })
+                .collect();
+            if denominator < 0 {
+                denominator = -denominator;
+                numerators.iter_mut().for_each(|n| *n = -*n);
+            }
+            let inside = constraints
+                .iter()
+                .all(|(normal, limit)| dot(normal, &numerators) <= limit * denominator);
+            if inside {
+                for axis in 0..N {
+                    let floor = numerators[axis].div_euclid(denominator);
+                    let ceil = -(-numerators[axis]).div_euclid(denominator);
+                    min[axis] = min[axis].min(to_coordinate(floor));
+                    max[axis] = max[axis].max(to_coordinate(ceil));
+                }
+            }
+        }
+        // The lines of the bounding box of the corners along its longest axis, where the region
+        // is the range of values for which all the constraints are strict.
+        let line_axis = (0..N)
+            .max_by_key(|axis| i128::from(max[*axis]) - i128::from(min[*axis]))
+            .unwrap_or(0);
+        let (first, last) = (i128::from(min[line_axis]), i128::from(max[line_axis]));
+        max[line_axis] = min[line_axis];
+        let lines = Bounds {
+            min: Point::from(min),
+            max: Point::from(max),
+        };
+        lines
+            .locations()
+            .map(|location| {
+                let mut location = wide(location);
+                location[line_axis] = 0;
+                let (mut first, mut last) = (first, last);
+                for (normal, limit) in &constraints {
+                    // The constraint is `factor * value < rest`.
+                    let factor = normal[line_axis];
+                    let rest = limit - dot(normal, &location);
+                    if factor > 0 {
+                        last = last.min((rest - 1).div_euclid(factor));
+                    } else if factor < 0 {
+                        first = first.max((-rest).div_euclid(-factor) + 1);
+                    } else if rest <= 0 {
+                        return 0;
+                    }
+                }
+                usize::try_from((last - first + 1).max(0)).unwrap_or(usize::MAX)
+            })
+            .fold(0, usize::saturating_add)
+    }
+}
+
+/// Count the locations of the region of the point with the given index by exploring it from the
+/// point, one step at a time along the axes and the diagonals.
+fn explore_region<const N: usize, M: Metric<N> + ?Sized>(
+    metric: &M,
+    points: &[Point<N>],
+    index: usize,
+) -> usize {
+    let start = points[index];
+    if closest_point(metric, points, start) != Label::Closest(index) {
+        return 0;
+    }
+    let steps = neighbor_steps::<N>();
+    let mut region = HashSet::new();
+    region.insert(start);
+    let mut to_visit = vec![start];
+    while let Some(location) = to_visit.pop() {
+        for step in &steps {
+            let next = location + *step;
+            if !region.contains(&next)
+                && closest_point(metric, points, next) == Label::Closest(index)
+            {
+                region.insert(next);
+                to_visit.push(next);
+            }
+        }
+    }
+    region.len()
+}
+
+/// Whether the region of each point is infinite because the point stays strictly closest while
+/// moving away from it forever, along a ray in the direction of one of the steps to its
+/// neighbors. This doesn't depend on how far apart the points are.
+///
+/// Far enough along the ray from `p`, the distance to another point `q` must be the distance to
+/// `p` plus `excess(step, p - q)`.
+fn escaping_regions<const N: usize>(
+    points: &[Point<N>],
+    excess: impl Fn(&Point<N>, &[i128]) -> i128,
+) -> Vec<bool> {
+    let steps = neighbor_steps::<N>();
+    (0..points.len())
+        .map(|index| {
+            steps.iter().any(|step| {
+                points
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, _)| *other != index)
+                    .all(|(_, q)| {
+                        excess(step, &difference(points[index], *q).collect::<Vec<_>>()) > 0
+                    })
+            })
+        })
+        .collect()
+}
+
+/// Whether each point appears more than once: its region is then empty.
+fn duplicated_points<const N: usize>(points: &[Point<N>]) -> Vec<bool> {
+    points
+        .iter()
+        .map(|point| points.iter().filter(|p| *p == point).count() > 1)
+        .collect()
+}
+
+/// Every set of `size` indices below `count`, in lexicographic order.
+fn combinations(count: usize, size: usize) -> Vec<Vec<usize>> {
+    let mut result = Vec::new();
+    if size == 0 || size > count {
+        return result;
+    }
+    let mut indices: Vec<usize> = (0..size).collect();
+    loop {
+        result.push(indices.clone());
+        // Advance the last index that can still move, and put the following ones right after it.
+        match (0..size).rev().find(|i| indices[*i] < count - size + i) {
+            None => return result,
+            Some(i) => {
+                indices[i] += 1;
+                for j in i + 1..size {
+                    indices[j] = indices[j - 1] + 1;
+                }
+            }
+        }
+    }
+}
+
+/// The coordinates of a point, wide enough for the products of the exact computations.
+fn wide<const N: usize>(point: Point<N>) -> Vec<i128> {
+    point.coordinates.iter().map(|c| i128::from(*c)).collect()
+}
+
+/// The differences between the coordinates of two points along each axis.
+fn difference<const N: usize>(a: Point<N>, b: Point<N>) -> impl Iterator<Item = i128> {
+    (0..N).map(move |axis| i128::from(a.coordinates[axis]) - i128::from(b.coordinates[axis]))
+}
+
+/// A coordinate of a corner of a region, clamped to the locations of the grid.
+///
+/// The corners of the finite regions are within 64 bits unless the points are both far apart
+/// and almost aligned: only the part of the region on the grid is counted.
+fn to_coordinate(value: i128) -> i64 {
+    i64::try_from(value).unwrap_or(if value < 0 { i64::MIN } else { i64::MAX })
+}
+
+/// Scalar product of two vectors.
+fn dot(a: &[i128], b: &[i128]) -> i128 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Normal of the hyperplane through the origin and the `N - 1` last vectors (the first one is the
+/// origin): the generalized cross product, zero when they are not independent.
+fn normal<const N: usize>(vectors: &[Point<N>]) -> Vec<i128> {
+    (0..N)
+        .map(|axis| {
+            let minor: Vec<Vec<i128>> = vectors[1..]
+                .iter()
+                .map(|v| {
+                    (0..N)
+                        .filter(|c| *c != axis)
+                        .map(|c| i128::from(v.coordinates[c]))
+                        .collect()
+                })
+                .collect();
+            let sign = if axis % 2 == 0 { 1 } else { -1 };
+            sign * determinant(minor)
+        })
+        .collect()
+}
+
+/// Exact determinant of a square integer matrix (Bareiss algorithm).
+fn determinant(mut matrix: Vec<Vec<i128>>) -> i128 {
+    let size = matrix.len();
+    let mut sign = 1;
+    let mut previous = 1;
+    for k in 0..size {
+        if matrix[k][k] == 0 {
+            match (k + 1..size).find(|i| matrix[*i][k] != 0) {
+                None => return 0,
+                Some(i) => {
+                    matrix.swap(k, i);
+                    sign = -sign;
+                }
+            }
+        }
+        for i in k + 1..size {
+            for j in k + 1..size {
+                matrix[i][j] =
+                    (matrix[i][j] * matrix[k][k] - matrix[i][k] * matrix[k][j]) / previous;
+            }
+        }
+        previous = matrix[k][k];
+    }
+    if size == 0 {
+        1
+    } else {
+        sign * matrix[size - 1][size - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{example_points, points};
+
+    /// Whether the region of each point reaches the edge of the bounding box of the points
+    /// extended by `margin`.
+    fn touches_edge<const N: usize, M: Metric<N>>(
+        metric: &M,
+        points: &[Point<N>],
+        margin: i64,
+    ) -> Vec<bool> {
+        let mut infinite = vec![false; points.len()];
+        for face in Bounds::around(points, margin).faces() {
+            for location in face.locations() {
+                if let Label::Closest(index) = closest_point(metric, points, location) {
+                    infinite[index] = true;
+                }
+            }
+        }
+        infinite
+    }
+
+    /// Size of each region on a large box around the points, or None if it touches its edge.
+    fn brute_force<const N: usize, M: Metric<N>>(
+        metric: M,
+        points: &[Point<N>],
+        margin: i64,
+    ) -> Vec<Option<usize>> {
+        let infinite = touches_edge(&metric, points, margin);
+        let mut sizes = vec![0; points.len()];
+        for location in Bounds::around(points, margin).locations() {
+            if let Label::Closest(index) = closest_point(&metric, points, location) {
+                sizes[index] += 1;
+            }
+        }
+        sizes
+            .into_iter()
+            .zip(infinite)
+            .map(|(size, infinite)| if infinite { None } else { Some(size) })
+            .collect()
+    }
+
+    #[test]
+    fn test_distances() {
+        let (a, b) = (Point::new(1, -2), Point::new(4, 2));
+        assert_eq!(7, Manhattan.distance(a, b));
+        assert_eq!(4, Chebyshev.distance(a, b));
+        assert_eq!(25, SquaredEuclidean.distance(a, b));
+        assert_eq!(25, (&SquaredEuclidean as &dyn Metric).distance(b, a));
+        let (a, b) = (Point::from([1, 2, 3]), Point::from([0, 4, 7]));
+        assert_eq!(7, Manhattan.distance(a, b));
+        assert_eq!(4, Chebyshev.distance(a, b));
+        assert_eq!(21, SquaredEuclidean.distance(a, b));
+        let (min, max) = (i64::from(i32::MIN), i64::from(i32::MAX));
+        let (a, b) = (Point::new(min, min), Point::new(max, max));
+        let span = i128::from(u32::MAX);
+        assert_eq!(2 * span, Manhattan.distance(a, b));
+        assert_eq!(span, Chebyshev.distance(a, b));
+        assert_eq!(2 * span * span, SquaredEuclidean.distance(b, a));
+    }
+
+    #[test]
+    fn test_manhattan_regions() {
+        assert_eq!(
+            vec![None, None, None, Some(9), Some(17), None],
+            Manhattan.finite_region_sizes(&example_points())
+        );
+    }
+
+    #[test]
+    fn test_manhattan_infinite_regions() {
+        // The middle point is not extreme along any diagonal, but it is the closest going up.
+        let points = points(&[(0, 0), (10, 0), (5, 1), (5, -4)]);
+        assert_eq!(
+            vec![true, true, true, true],
+            Manhattan.infinite_regions(&points)
+        );
+        assert_eq!(
+            touches_edge(&Manhattan, &points, 0),
+            Manhattan.infinite_regions(&points)
+        );
+    }
+
+    #[test]
+    fn test_spread_points() {
+        // A point surrounded by four others, and points in the corners of the 32 bits range.
+        let (min, max) = (i64::from(i32::MIN), i64::from(i32::MAX));
+        let points = points(&[
+            (0, 0),
+            (2, 0),
+            (-2, 0),
+            (0, 2),
+            (0, -2),
+            (min, min),
+            (max, max),
+            (min, max),
+            (max, min),
+        ]);
+        let mut infinite = vec![true; points.len()];
+        infinite[0] = false;
+        assert_eq!(infinite, Manhattan.infinite_regions(&points));
+        // With the Chebyshev distance, the corners surround the others.
+        assert_eq!(
+            vec![false, false, false, false, false, true, true, true, true],
+            Chebyshev.infinite_regions(&points)
+        );
+        assert_eq!(Some(1), Manhattan.finite_region_sizes(&points)[0]);
+    }
+
+    #[test]
+    fn test_chebyshev_regions() {
+        // A point surrounded by four others.
+        let points = points(&[(0, 0), (2, 0), (-2, 0), (0, 2), (0, -2)]);
+        assert_eq!(
+            vec![false, true, true, true, true],
+            Chebyshev.infinite_regions(&points)
+        );
+        for points in &[example_points(), points] {
+            assert_eq!(
+                brute_force(Chebyshev, points, 30),
+                Chebyshev.finite_region_sizes(points)
+            );
+        }
+    }
+
+    #[test]
+    fn test_euclidean_infinite_regions() {
+        // The middle of an edge of the hull has an infinite strip, the center is surrounded.
+        let square = points(&[(0, 0), (2, 0), (4, 0), (2, 2), (4, 4), (0, 4)]);
+        assert_eq!(
+            vec![true, true, true, false, true, true],
+            SquaredEuclidean.infinite_regions(&square)
+        );
+        let aligned = points(&[(0, 0), (1, 1), (2, 2), (2, 2)]);
+        assert_eq!(
+            vec![true, true, false, false],
+            SquaredEuclidean.infinite_regions(&aligned)
+        );
+    }
+
+    #[test]
+    fn test_euclidean_regions() {
+        // The region of the point in the middle goes down to (2, -1.5).
+        let points = points(&[(0, 0), (4, 0), (2, 1), (0, 8), (4, 8)]);
+        assert_eq!(
+            brute_force(SquaredEuclidean, &points, 10),
+            SquaredEuclidean.finite_region_sizes(&points)
+        );
+        assert_eq!(
+            vec![None, None, None, Some(10), Some(16), None],
+            SquaredEuclidean.finite_region_sizes(&example_points())
+        );
+    }
+
+    #[test]
+    fn test_to_coordinate() {
+        assert_eq!(-5, to_coordinate(-5));
+        assert_eq!(i64::MAX, to_coordinate(i128::from(i64::MAX) + 1));
+        assert_eq!(i64::MIN, to_coordinate(-(1 << 90)));
+    }
+
+    #[test]
+    fn test_three_dimensions() {
+        // A point surrounded by six others, one on each side.
+        let mut points = vec![Point::from([0, 0, 0])];
+        for axis in 0..3 {
+            for side in &[-3, 3] {
+                let mut point = Point::from([0, 0, 0]);
+                point.coordinates[axis] = *side;
+                points.push(point);
+            }
+        }
+        let sizes = Manhattan.finite_region_sizes(&points);
+        assert!(sizes[0].is_some() && sizes[1..].iter().all(Option::is_none));
+        assert_eq!(brute_force(Manhattan, &points, 0), sizes);
+        for metric in &[&Chebyshev as &dyn Metric<3>, &SquaredEuclidean] {
+            assert_eq!(
+                brute_force(metric, &points, 10),
+                metric.finite_region_sizes(&points)
+            );
+        }
+    }
+
+    #[test]
+    fn test_determinant() {
+        assert_eq!(-2, determinant(vec![vec![1, 2], vec![3, 4]]));
+        assert_eq!(0, determinant(vec![vec![1, 2], vec![2, 4]]));
+        assert_eq!(
+            -2,
+            determinant(vec![vec![0, 1, 2], vec![1, 0, 3], vec![4, -3, 8]])
+        );
+        assert_eq!(
+            vec![0, 0, 1],
+            normal(&[Point::from([0, 0, 0]), Point::from([1, 0, 0]), Point::from([0, 1, 0])])
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use crate::bounds::Bounds;
+use crate::metric::Metric;
+use crate::Point;
+
+/// Sums of the distances to a set of coordinates along one axis.
+///
+/// The Manhattan distance is separable: the sum of the distances from a location to all the
+/// points is the sum over the x axis plus the sum over the y axis, each computed independently.
+#[derive(Debug, Clone)]
+struct AxisDistances {
+    /// The coordinates, sorted.
+    sorted: Vec<i64>,
+    /// `prefix[i]` is the sum of the `i` first sorted coordinates.
+    prefix: Vec<i64>,
+}
+
+impl AxisDistances {
+    fn new<I: Iterator<Item = i64>>(coordinates: I) -> Self {
+        let mut sorted = coordinates.collect::<Vec<_>>();
+        sorted.sort_unstable();
+        let mut prefix = Vec::with_capacity(sorted.len() + 1);
+        prefix.push(0);
+        for c in &sorted {
+            prefix.push(prefix.last().unwrap() + c);
+        }
+        Self { sorted, prefix }
+    }
+
+    /// Sum of the distances from `c` to all the coordinates, in O(log n).
+    fn sum(&self, c: i64) -> i64 {
+        let n = self.sorted.len();
+        // Number of coordinates strictly before `c`.
+        let before = self.sorted.partition_point(|v| *v < c);
+        let left = c * before as i64 - self.prefix[before];
+        let right = (self.prefix[n] - self.prefix[before]) - c * (n - before) as i64;
+        left + right
+    }
+
+    /// The range of coordinates whose sum of distances is at most `limit`, if any.
+    fn range_within(&self, limit: i64) -> Option<(i64, i64)> {
+        let n = self.sorted.len() as i64;
+        // Outside of the coordinates, the sum grows by n per step: it is over the limit at these
+        // bounds.
+        let low = self.sorted[0] - limit / n - 1;
+        let high = self.sorted[self.sorted.len() - 1] + limit / n + 1;
+        convex_range_within(|c| i128::from(self.sum(c)), low, high, i128::from(limit))
+    }
+}
+
+/// The range of values in `[low, high]` where the convex function `f` is at most `limit`, if any.
+///
+/// The function decreases down to its minimum, then increases, so the minimum and each end of
+/// the range are found with a binary search.
+fn convex_range_within<F: Fn(i64) -> i128>(
+    f: F,
+    low: i64,
+    high: i64,
+    limit: i128,
+) -> Option<(i64, i64)> {
+    if low > high {
+        return None;
+    }
+    // The minimum is at the first value where the function stops decreasing.
+    let (mut first, mut last) = (low, high);
+    while first < last {
+        let middle = first + (last - first) / 2;
+        if f(middle + 1) >= f(middle) {
+            last = middle;
+        } else {
+            first = middle + 1;
+        }
+    }
+    let minimum = first;
+    if f(minimum) > limit {
+        return None;
+    }
+    // First value within the limit, in [low, minimum].
+    let (mut bad, mut good) = (low - 1, minimum);
+    while good - bad > 1 {
+        let middle = bad + (good - bad) / 2;
+        if f(middle) <= limit {
+            good = middle;
+        } else {
+            bad = middle;
+        }
+    }
+    let first = good;
+    // Last value within the limit, in [minimum, high].
+    let (mut good, mut bad) = (minimum, high + 1);
+    while bad - good > 1 {
+        let middle = good + (bad - good) / 2;
+        if f(middle) <= limit {
+            good = middle;
+        } else {
+            bad = middle;
+        }
+    }
+    Some((first, good))
+}
+
+/// Count the locations whose sum of Manhattan distances to all the `points` is at most
+/// `max_distance`, wherever they are.
+///
+/// For each value along the first axis where the sum along it is within the limit, the rest of
+/// the limit is shared by the other axes, down to the last one where the valid values form a
+/// range, found with the per-axis sums. In two dimensions, this takes
+/// O((width of the region + log(max_distance)) * log n).
+///
+/// There are no such locations without points.
+pub(crate) fn count_within_manhattan_distance<const N: usize>(
+    points: &[Point<N>],
+    max_distance: i32,
+) -> usize {
+    if points.is_empty() || N == 0 {
+        return 0;
+    }
+    let axes: Vec<AxisDistances> = (0..N)
+        .map(|axis| AxisDistances::new(points.iter().map(|p| p.coordinates[axis])))
+        .collect();
+    count_within_axes(&axes, i64::from(max_distance))
+}
+
+/// Count the locations whose sum of the per-axis sums is at most `limit`.
+fn count_within_axes(axes: &[AxisDistances], limit: i64) -> usize {
+    let (first, last) = match axes[0].range_within(limit) {
+        None => return 0,
+        Some(range) => range,
+    };
+    if axes.len() == 1 {
+        return (last - first + 1) as usize;
+    }
+    (first..=last)
+        .map(|c| count_within_axes(&axes[1..], limit - axes[0].sum(c)))
+        .sum()
+}
+
+/// Count the locations whose sum of distances to all the `points` is at most `max_distance`,
+/// with any metric.
+///
+/// The distances are at least the differences along each axis, so the region is within the
+/// ranges where the per-axis sums are within the limit. Along the last axis, the sum is convex:
+/// the valid values form a range, found with binary searches. This takes
+/// O(area of the region along the other axes * log(max_distance) * n).
+pub(crate) fn count_within_distance<const N: usize, M: Metric<N> + ?Sized>(
+    metric: &M,
+    points: &[Point<N>],
+    max_distance: i32,
+) -> usize {
+    if points.is_empty() || N == 0 {
+        return 0;
+    }
+    let mut min = [0; N];
+    let mut max = [0; N];
+    for axis in 0..N {
+        let sums = AxisDistances::new(points.iter().map(|p| p.coordinates[axis]));
+        match sums.range_within(i64::from(max_distance)) {
+            None => return 0,
+            Some((first, last)) => {
+                min[axis] = first;
+                max[axis] = last;
+            }
+        }
+    }
+    let last_axis = N - 1;
+    let (first, last) = (min[last_axis], max[last_axis]);
+    // The other axes, with the last one fixed.
+    max[last_axis] = min[last_axis];
+    let others = Bounds {
+        min: Point::from(min),
+        max: Point::from(max),
+    };
+    others
+        .locations()
+        .filter_map(|location| {
+            let sum = |c: i64| {
+                let mut location = location;
+                location.coordinates[last_axis] = c;
+                points.iter().map(|p| metric.distance(location, *p)).sum()
+            };
+            convex_range_within(sum, first, last, i128::from(max_distance))
+        })
+        .map(|(first, last)| (last - first + 1) as usize)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::{Chebyshev, Manhattan, SquaredEuclidean};
+    use crate::tests::example_points;
+
+    /// Count the locations by checking every location in the given square.
+    fn brute_force<M: Metric>(
+        metric: M,
+        points: &[Point],
+        max_distance: i32,
+        extent: i64,
+    ) -> usize {
+        let mut count = 0;
+        for x in -extent..=extent {
+            for y in -extent..=extent {
+                let sum: i128 = points
+                    .iter()
+                    .map(|p| metric.distance(Point::new(x, y), *p))
+                    .sum();
+                if sum <= i128::from(max_distance) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn test_axis_sum() {
+        let axis = AxisDistances::new(vec![1, 4, 4, 10].into_iter());
+        assert_eq!(3 + 6 + 6 + 12, axis.sum(-2));
+        assert_eq!(3 + 6, axis.sum(4));
+        assert_eq!(10 + 7 + 7 + 1, axis.sum(11));
+        assert_eq!(Some((4, 4)), axis.range_within(9));
+        assert_eq!(Some((3, 5)), axis.range_within(12));
+        assert_eq!(Some((2, 6)), axis.range_within(14));
+        assert_eq!(None, axis.range_within(8));
+    }
+
+    #[test]
+    fn test_convex_range_within() {
+        let f = |x: i64| i128::from((x - 3) * (x - 3));
+        assert_eq!(Some((1, 5)), convex_range_within(f, -10, 10, 4));
+        assert_eq!(Some((1, 2)), convex_range_within(f, -10, 2, 4));
+        assert_eq!(None, convex_range_within(f, 6, 10, 4));
+        assert_eq!(None, convex_range_within(f, 1, 0, 4));
+    }
+
+    #[test]
+    fn test_count_within_manhattan_distance() {
+        let points = example_points();
+        // The example of the subject uses "less than 32".
+        assert_eq!(16, count_within_manhattan_distance(&points, 31));
+        // The region goes beyond the points.
+        for max_distance in &[0, 30, 60, 100, 200] {
+            assert_eq!(
+                brute_force(Manhattan, &points, *max_distance, 50),
+                count_within_manhattan_distance(&points, *max_distance)
+            );
+        }
+        assert_eq!(1, count_within_manhattan_distance(&[Point::new(-3, 2)], 0));
+        assert_eq!(5, count_within_manhattan_distance(&[Point::new(-3, 2)], 1));
+        assert_eq!(0, count_within_manhattan_distance::<2>(&[], 10));
+    }
+
+    #[test]
+    fn test_count_within_distance() {
+        let points = example_points();
+        for max_distance in &[0, 20, 31, 60, 100] {
+            let expected = brute_force(Manhattan, &points, *max_distance, 50);
+            assert_eq!(expected, count_within_distance(&Manhattan, &points, *max_distance));
+            let expected = brute_force(Chebyshev, &points, *max_distance, 50);
+            assert_eq!(expected, Chebyshev.count_within_distance(&points, *max_distance));
+        }
+        for max_distance in &[0, 100, 300, 1000] {
+            let expected = brute_force(SquaredEuclidean, &points, *max_distance, 50);
+            assert_eq!(expected, SquaredEuclidean.count_within_distance(&points, *max_distance));
+        }
+        // Within a distance of 2 of a single point: a square for Chebyshev, a disc otherwise.
+        assert_eq!(25, Chebyshev.count_within_distance(&[Point::new(-3, 2)], 2));
+        assert_eq!(13, SquaredEuclidean.count_within_distance(&[Point::new(-3, 2)], 4));
+        assert_eq!(0, Metric::<2>::count_within_distance(&Chebyshev, &[], 10));
+    }
+
+    #[test]
+    fn test_three_dimensions() {
+        let points = [Point::from([1, 1, 0]), Point::from([4, 0, 2]), Point::from([2, 5, 1])];
+        for max_distance in &[0, 10, 20] {
+            let mut expected = 0;
+            for location in Bounds::around(&points, 20).locations() {
+                let sum: i128 = points.iter().map(|p| Manhattan.distance(location, *p)).sum();
+                if sum <= i128::from(*max_distance) {
+                    expected += 1;
+                }
+            }
+            assert_eq!(expected, Manhattan.count_within_distance(&points, *max_distance));
+            assert_eq!(expected, count_within_distance(&Manhattan, &points, *max_distance));
+        }
+        // A single point: an octahedron of 1 + 6 + 18 locations within a distance of 2.
+        assert_eq!(25, Manhattan.count_within_distance(&[Point::from([0, 0, 0])], 2));
+    }
+
+    #[test]
+    fn test_large_distance() {
+        // With a single point, the region is a diamond of 2d(d+1)+1 locations.
+        let d: i64 = 300_000;
+        assert_eq!(
+            (2 * d * (d + 1) + 1) as usize,
+            count_within_manhattan_distance(&[Point::new(0, 0)], d as i32)
+        );
+    }
+
+    #[test]
+    fn test_extreme_coordinates() {
+        let (min, max) = (i64::from(i32::MIN), i64::from(i32::MAX));
+        // The regions go beyond the coordinates of the input.
+        for corner in &[Point::new(max, max), Point::new(min, min)] {
+            assert_eq!(13, Manhattan.count_within_distance(&[*corner], 2));
+            assert_eq!(13, count_within_distance(&Manhattan, &[*corner], 2));
+            assert_eq!(25, Chebyshev.count_within_distance(&[*corner], 2));
+            assert_eq!(13, SquaredEuclidean.count_within_distance(&[*corner], 4));
+        }
+        // The points are further apart than the largest distance.
+        let points = [Point::new(min, 0), Point::new(max, 0)];
+        for metric in &[&Manhattan as &dyn Metric, &Chebyshev, &SquaredEuclidean] {
+            assert_eq!(0, metric.count_within_distance(&points, i32::MAX));
+        }
+        assert_eq!(0, count_within_distance(&Manhattan, &points, i32::MAX));
+    }
+}
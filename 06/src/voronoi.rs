@@ -0,0 +1,292 @@
+use std::io;
+use std::io::Write;
+
+use crate::bounds::Bounds;
+use crate::metric::{Manhattan, Metric};
+use crate::Point;
+
+/// Label of a location of the map.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Label {
+    /// The location is strictly closer to the point with this index than to any other.
+    Closest(usize),
+    /// The location is equally far from two or more points.
+    Tie,
+}
+
+/// Voronoi map in two dimensions: the label of each location of a rectangle, made of the bounding
+/// box of the points and a margin around it, for a given metric.
+///
+/// The regions are told apart by the labels alone: the ones that reach the edge of the map are
+/// considered infinite, the others are entirely on it. The margin needed for this to match the
+/// metric depends on it: none for the Manhattan distance, more than the span of the points for the
+/// Chebyshev distance. `Metric::finite_region_sizes` gives the exact regions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoronoiMap {
+    /// The locations of the map.
+    bounds: Bounds<2>,
+    /// Labels of the locations, row by row.
+    labels: Vec<Label>,
+    /// The points, in the order of their labels.
+    points: Vec<Point>,
+}
+
+impl VoronoiMap {
+    /// Compute the map of the points with the Manhattan distance over their bounding box, extended
+    /// by `margin` on each side.
+    ///
+    /// For example, with the example of the puzzle:
+    /// ```
+    /// use lib::{Label, Point, VoronoiMap};
+    ///
+    /// let points = [(1, 1), (1, 6), (8, 3), (3, 4), (5, 5), (8, 9)];
+    /// let points: Vec<Point> = points.iter().map(|&p| Point::from(p)).collect();
+    /// let map = VoronoiMap::new(&points, 0);
+    /// assert_eq!(Some(Label::Closest(3)), map.label_at(Point::new(2, 3)));
+    /// assert_eq!(Some(Label::Tie), map.label_at(Point::new(5, 1)));
+    /// assert_eq!(vec![None, None, None, Some(9), Some(17), None], map.finite_region_sizes());
+    /// ```
+    pub fn new(points: &[Point], margin: i32) -> Self {
+        Self::with_metric(points, margin, Manhattan)
+    }
+
+    /// Compute the map of the points with the given metric over their bounding box, extended by
+    /// `margin` on each side.
+    ///
+    /// For example:
+    /// ```
+    /// use lib::{Chebyshev, Label, Point, VoronoiMap};
+    ///
+    /// let points = [Point::new(0, 0), Point::new(4, 3)];
+    /// let map = VoronoiMap::with_metric(&points, 0, Chebyshev);
+    /// assert_eq!(Some(Label::Closest(0)), map.label_at(Point::new(1, 2)));
+    /// assert_eq!(Some(Label::Tie), map.label_at(Point::new(2, 2)));
+    /// ```
+    pub fn with_metric<M: Metric>(points: &[Point], margin: i32, metric: M) -> Self {
+        let bounds = Bounds::around(points, i64::from(margin));
+        let labels = bounds
+            .locations()
+            .map(|location| closest_point(&metric, points, location))
+            .collect();
+        Self {
+            bounds,
+            labels,
+            points: points.to_vec(),
+        }
+    }
+
+    /// Width of the map.
+    pub fn width(&self) -> usize {
+        self.bounds.sizes()[0]
+    }
+
+    /// Height of the map.
+    pub fn height(&self) -> usize {
+        self.bounds.sizes()[1]
+    }
+
+    /// The label of the location, if it is on the map.
+    pub fn label_at(&self, location: Point) -> Option<Label> {
+        self.bounds.index(location).map(|index| self.labels[index])
+    }
+
+    /// Number of locations closest to each point, on the map.
+    pub fn region_sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![0; self.points.len()];
+        for label in &self.labels {
+            if let Label::Closest(index) = label {
+                sizes[*index] += 1;
+            }
+        }
+        sizes
+    }
+
+    /// Whether the region of each point reaches the edge of the map, and is considered infinite.
+    pub fn infinite_regions(&self) -> Vec<bool> {
+        let mut infinite = vec![false; self.points.len()];
+        for location in self.bounds.faces().flat_map(Bounds::locations) {
+            if let Some(Label::Closest(index)) = self.label_at(location) {
+                infinite[index] = true;
+            }
+        }
+        infinite
+    }
+
+    /// Size of the region of each point, or None if it reaches the edge of the map.
+    pub fn finite_region_sizes(&self) -> Vec<Option<usize>> {
+        self.region_sizes()
+            .into_iter()
+            .zip(self.infinite_regions())
+            .map(|(size, infinite)| if infinite { None } else { Some(size) })
+            .collect()
+    }
+
+    /// Write the map as a binary PPM image, with one color per region, darker for the infinite
+    /// regions. The points are white, and the ties are black.
+    pub fn write_ppm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width(), self.height())?;
+        let infinite = self.infinite_regions();
+        let mut row = Vec::with_capacity(3 * self.width());
+        for (location, label) in self.bounds.locations().zip(&self.labels) {
+            let color = match label {
+                Label::Tie => TIE_COLOR,
+                Label::Closest(index) if self.points[*index] == location => POINT_COLOR,
+                Label::Closest(index) => region_color(*index, infinite[*index]),
+            };
+            row.extend_from_slice(&color);
+            if row.len() == 3 * self.width() {
+                writer.write_all(&row)?;
+                row.clear();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Color of the locations equally far from several points.
+const TIE_COLOR: [u8; 3] = [0, 0, 0];
+/// Color of the points themselves.
+const POINT_COLOR: [u8; 3] = [255, 255, 255];
+
+/// Color of the region of the point with the given index: the hues are spread with the golden
+/// ratio, so that consecutive regions have very different colors.
+fn region_color(index: usize, infinite: bool) -> [u8; 3] {
+    let hue = (index as f64 * 0.618_033_988_75).fract() * 6.0;
+    let value = if infinite { 130.0 } else { 240.0 };
+    let saturation = 0.6;
+    // Standard HSV to RGB conversion.
+    let chroma = value * saturation;
+    let second = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, second, 0.0),
+        1 => (second, chroma, 0.0),
+        2 => (0.0, chroma, second),
+        3 => (0.0, second, chroma),
+        4 => (second, 0.0, chroma),
+        _ => (chroma, 0.0, second),
+    };
+    let min = value - chroma;
+    [(r + min) as u8, (g + min) as u8, (b + min) as u8]
+}
+
+/// Find the point strictly closest to the location, if there is one.
+pub(crate) fn closest_point<const N: usize, M: Metric<N> + ?Sized>(
+    metric: &M,
+    points: &[Point<N>],
+    location: Point<N>,
+) -> Label {
+    let mut best = Label::Tie;
+    let mut best_distance = None;
+    for (index, point) in points.iter().enumerate() {
+        let distance = metric.distance(*point, location);
+        match best_distance {
+            Some(d) if d < distance => {}
+            Some(d) if d == distance => best = Label::Tie,
+            _ => {
+                best = Label::Closest(index);
+                best_distance = Some(distance);
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::{Chebyshev, SquaredEuclidean};
+    use crate::tests::{example_points, points};
+
+    #[test]
+    fn test_labels() {
+        // The grid of the subject, starting at 0, 0.
+        let expected = [
+            "aaaaa.cccc",
+            "aAaaa.cccc",
+            "aaaddecccc",
+            "aadddeccCc",
+            "..dDdeeccc",
+            "bb.deEeecc",
+            "bBb.eeee..",
+            "bbb.eeefff",
+            "bbb.eeffff",
+            "bbb.ffffFf",
+        ];
+        let map = VoronoiMap::new(&example_points(), 1);
+        for (y, line) in expected.iter().enumerate() {
+            let labels: String = (0..10)
+                .map(|x| match map.label_at(Point::new(x, y as i64)).unwrap() {
+                    Label::Tie => '.',
+                    Label::Closest(i) => (b'a' + i as u8) as char,
+                })
+                .collect();
+            assert_eq!(line.to_lowercase(), labels);
+        }
+        assert_eq!(None, map.label_at(Point::new(10, 0)));
+        assert_eq!(None, map.label_at(Point::new(-1, 0)));
+    }
+
+    #[test]
+    fn test_finite_regions() {
+        for margin in 0..3 {
+            let map = VoronoiMap::new(&example_points(), margin);
+            assert_eq!(
+                vec![false, false, false, true, true, false],
+                map.finite_region_sizes()
+                    .iter()
+                    .map(Option::is_some)
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(Some(17), map.finite_region_sizes()[4]);
+        }
+    }
+
+    #[test]
+    fn test_tie_everywhere() {
+        // Two points on a diagonal: the ties cover a whole quadrant.
+        let map = VoronoiMap::new(&[Point::new(0, 0), Point::new(1, 1)], 1);
+        assert_eq!(Some(Label::Tie), map.label_at(Point::new(-1, 2)));
+        assert_eq!(Some(Label::Tie), map.label_at(Point::new(1, 0)));
+        assert_eq!(vec![true, true], map.infinite_regions());
+    }
+
+    #[test]
+    fn test_metrics() {
+        // Points on the diagonal of their bounding box, and around it.
+        let diagonal = points(&[(0, 0), (2, 2), (4, 4), (6, 6), (8, 8), (0, 8), (8, 0), (3, 5)]);
+        let aligned = points(&[(0, 0), (1, 1), (2, 2), (3, 3)]);
+        let mut seed: u64 = 11;
+        let mut next = |modulo: u64| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            ((seed >> 33) % modulo) as i64
+        };
+        let random = (0..12).map(|_| Point::new(next(15), next(15))).collect::<Vec<_>>();
+        for points in &[example_points(), diagonal, aligned, random] {
+            assert_eq!(
+                Manhattan.finite_region_sizes(points),
+                VoronoiMap::new(points, 0).finite_region_sizes()
+            );
+            for metric in &[&Manhattan as &dyn Metric, &Chebyshev, &SquaredEuclidean] {
+                // On a map large enough, the finite regions are away from the edge.
+                assert_eq!(
+                    metric.finite_region_sizes(points),
+                    VoronoiMap::with_metric(points, 40, metric).finite_region_sizes()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let map = VoronoiMap::new(&[Point::new(0, 0), Point::new(2, 0)], 0);
+        let mut output = Vec::new();
+        map.write_ppm(&mut output).unwrap();
+        let mut expected = b"P6\n3 1\n255\n".to_vec();
+        expected.extend_from_slice(&POINT_COLOR);
+        expected.extend_from_slice(&TIE_COLOR);
+        expected.extend_from_slice(&POINT_COLOR);
+        assert_eq!(expected, output);
+        assert_ne!(region_color(0, false), region_color(1, false));
+        assert_ne!(region_color(0, false), region_color(0, true));
+    }
+}
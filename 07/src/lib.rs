@@ -1,4 +1,3 @@
-#![feature(existential_type)]
 #[macro_use]
 extern crate nom;
 #[macro_use]
@@ -7,19 +6,19 @@ extern crate derive_more;
 extern crate boolinator;
 
 use std::iter::Iterator;
+use std::fmt;
 use std::fmt::Debug;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap};
 use boolinator::Boolinator;
 
-const ALPHABET_SIZE: usize = 26;
-
+/// Identifier of a step, given by a `StepTable`.
 #[derive(Eq, PartialEq, Debug, Into, Copy, Clone)]
-pub struct NodeId(u8);
+pub struct NodeId(usize);
 
 /// Reverse order: max returns the smallest element.
 impl std::cmp::PartialOrd for NodeId {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        other.0.partial_cmp(&self.0)
+        Some(self.cmp(other))
     }
 }
 /// Reverse order: max returns the smallest element.
@@ -29,32 +28,247 @@ impl std::cmp::Ord for NodeId {
     }
 }
 
-/// Parse a NodeId: Map 'A' to 0, 'B' to 1, etc...
-named!(node_id <&str, NodeId>,
-       map!(take!(1), |c| NodeId(c.bytes().next().unwrap() - b'A'))
-);
+/// The names of the steps, interned: each name has a `NodeId`, and the IDs follow the
+/// lexicographic order of the names, so that sorting the IDs sorts the names.
+///
+/// For example:
+/// ```
+/// let table = lib::StepTable::new(vec!["link", "compile-core", "link", "B"]);
+/// assert_eq!(3, table.len());
+/// let names: Vec<&str> = table.ids().map(|id| table.name(id)).collect();
+/// assert_eq!(vec!["B", "compile-core", "link"], names);
+/// assert_eq!(Some("link"), table.id("link").map(|id| table.name(id)));
+/// assert_eq!(None, table.id("test"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StepTable {
+    /// The names, sorted, indexed by ID.
+    names: Vec<String>,
+    ids: HashMap<String, NodeId>,
+}
+
+impl StepTable {
+    /// Intern the names, given in any order and possibly several times.
+    pub fn new<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Self {
+        let mut names: Vec<String> = names.into_iter().map(String::from).collect();
+        names.sort_unstable();
+        names.dedup();
+        let ids = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), NodeId(i)))
+            .collect();
+        Self { names, ids }
+    }
+
+    /// The ID of the step with the given name, if it is in the table.
+    pub fn id(&self, name: &str) -> Option<NodeId> {
+        self.ids.get(name).cloned()
+    }
+
+    /// The name of the step with the given ID.
+    pub fn name(&self, id: NodeId) -> &str {
+        &self.names[id.0]
+    }
+
+    /// Iterate over the IDs, in the order of the names.
+    pub fn ids(&self) -> impl Iterator<Item = NodeId> {
+        (0..self.names.len()).map(NodeId)
+    }
+
+    /// Number of steps.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Whether there are no steps.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
 
 /// A constraint is an edge in our directed graph: the first one must come before the second one.
 type Constraint = (NodeId, NodeId);
 
-/// Parse a constraint from a sentence.
-/// ```
-/// assert_eq!(constraint("Step C must be finished before step A can begin.").unwrap().1,
-///            (NodeId(2), NodeId(0)));
-/// ```
-named!(constraint <&str, Constraint>,
+// Parse a constraint from a sentence, as the names of the two steps. The names can be any string.
+named!(constraint <&str, (&str, &str)>,
     do_parse!(
         tag!("Step ") >>
-        from: node_id >>
+        from: take_until!(" must be finished before step ") >>
         tag!(" must be finished before step ") >>
-        to: node_id >>
+        to: take_until!(" can begin.") >>
         tag!(" can begin.") >>
         (from, to)
 ));
 
-/// Parse a list of constraints from a list of strings.
-fn parse_constraints(lines: &[String]) -> Vec<Constraint> {
-    lines.iter().map(|l| constraint(l).unwrap().1).collect()
+/// Error while parsing the constraints: a line is not a valid constraint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Line of the error, starting at 1.
+    pub line: usize,
+    /// Text of the line.
+    pub text: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: invalid constraint {:?}", self.line, self.text)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Error while ordering the steps: some of them can never begin, because they are on a cycle of
+/// constraints or they come after one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleError {
+    /// Names of the steps that can never begin, sorted.
+    pub steps: Vec<String>,
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cyclic constraints, these steps can never begin: {}", self.steps.join(", "))
+    }
+}
+
+impl std::error::Error for CycleError {}
+
+/// Error while reading the constraints from lines and ordering the steps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    Parse(ParseError),
+    Cycle(CycleError),
+}
+
+impl From<ParseError> for BuildError {
+    fn from(err: ParseError) -> Self {
+        BuildError::Parse(err)
+    }
+}
+
+impl From<CycleError> for BuildError {
+    fn from(err: CycleError) -> Self {
+        BuildError::Cycle(err)
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::Parse(err) => write!(f, "{}", err),
+            BuildError::Cycle(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// Parse one line as a constraint, if it is a whole sentence and both steps have a name.
+fn parse_constraint(line: &str) -> Option<(&str, &str)> {
+    match constraint(line) {
+        Ok((rest, (from, to))) if rest.is_empty() && !from.is_empty() && !to.is_empty() => {
+            Some((from, to))
+        }
+        _ => None,
+    }
+}
+
+/// The steps of the instructions, and the constraints between them.
+///
+/// For example:
+/// ```
+/// let lines: Vec<String> = vec![
+///     "Step C must be finished before step A can begin.",
+///     "Step C must be finished before step B can begin.",
+/// ]
+/// .into_iter()
+/// .map(String::from)
+/// .collect();
+/// let instructions = lib::Instructions::parse(&lines).unwrap();
+/// assert_eq!(3, instructions.steps().len());
+/// assert_eq!(vec!["C", "A", "B"], instructions.build_order().unwrap());
+/// // Every step takes 10 seconds with 2 workers: A and B are done together.
+/// assert_eq!(Ok(20), instructions.build_time(2, |_| 10));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Instructions {
+    table: StepTable,
+    constraints: Vec<Constraint>,
+}
+
+impl Instructions {
+    /// Parse the constraints from a list of strings, one sentence per line. The blank lines are
+    /// skipped.
+    pub fn parse(lines: &[String]) -> Result<Self, ParseError> {
+        let names = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(i, l)| {
+                parse_constraint(l).ok_or_else(|| ParseError {
+                    line: i + 1,
+                    text: l.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let table = StepTable::new(names.iter().flat_map(|(from, to)| vec![*from, *to]));
+        let constraints = names
+            .iter()
+            .map(|(from, to)| (table.id(from).unwrap(), table.id(to).unwrap()))
+            .collect();
+        Ok(Self { table, constraints })
+    }
+
+    /// The names of the steps.
+    pub fn steps(&self) -> &StepTable {
+        &self.table
+    }
+
+    /// Find the order of the steps with a single worker: among the steps that can begin, the
+    /// first name in lexicographic order comes first.
+    pub fn build_order(&self) -> Result<Vec<String>, CycleError> {
+        let graph = AnnotatedGraph::new(build_graph(&self.constraints, self.table.len()));
+        let order = find_build_order_from_graph(graph);
+        if order.len() < self.table.len() {
+            let mut done = vec![false; self.table.len()];
+            order.iter().for_each(|n| done[n.0] = true);
+            return Err(CycleError {
+                steps: self
+                    .table
+                    .ids()
+                    .filter(|n| !done[n.0])
+                    .map(|n| self.table.name(n).to_string())
+                    .collect(),
+            });
+        }
+        Ok(order
+            .into_iter()
+            .map(|n| self.table.name(n).to_string())
+            .collect())
+    }
+
+    /// Find the time to complete the steps with several workers, given the time each step takes
+    /// from its name (see `step_time` for the rule of the puzzle).
+    pub fn build_time<F: Fn(&str) -> usize>(
+        &self,
+        num_workers: usize,
+        step_time: F,
+    ) -> Result<usize, CycleError> {
+        // With a cycle, the workers would stop before all the steps are done.
+        self.build_order()?;
+        let graph = AnnotatedGraph::new(build_graph(&self.constraints, self.table.len()));
+        let node_times: Vec<usize> = self
+            .table
+            .ids()
+            .map(|n| step_time(self.table.name(n)))
+            .collect();
+        Ok(find_build_time_from_graph_with_workers(
+            graph,
+            &node_times,
+            num_workers,
+        ))
+    }
 }
 
 /// A node in our graph.
@@ -67,39 +281,38 @@ struct Node {
 }
 
 /// A simple graph is just the structure, with each node identified by a `NodeId`.
-///
-/// Since the `NodeId`s may not be dense, we only store Options in the vector.
 #[derive(Debug)]
 struct SimpleGraph {
-    nodes: Vec<Option<Node>>,
+    nodes: Vec<Node>,
 }
 
 impl SimpleGraph {
-    /// Mutable access to a node. If the node doesn't exist, it will be created.
+    /// Mutable access to a node.
     fn get_mut_node(&mut self, key: NodeId) -> &mut Node {
-        let cell = &mut self.nodes[key.0 as usize];
-        if cell.is_none() {
-            *cell = Some(Node {
-                children: vec![],
-                num_parents: 0,
-            });
-        }
-        self.nodes[key.0 as usize].as_mut().unwrap()
+        &mut self.nodes[key.0]
     }
-    /// Read-only access to a node. If the node doesn't exist, panic.
+    /// Read-only access to a node.
     fn get_node(&self, key: NodeId) -> &Node {
-        self.nodes[key.0 as usize].as_ref().unwrap()
+        &self.nodes[key.0]
     }
 
-    /// Iterate over all the (defined) nodes in the graph.
+    /// Iterate over all the nodes in the graph.
     fn iter(&self) -> impl Iterator<Item = &Node> {
-        self.nodes.iter().flatten()
+        self.nodes.iter()
     }
 }
 
-/// Build a graph from a list of constraints.
-fn build_graph(constraints: &[Constraint]) -> SimpleGraph {
-    let mut graph = SimpleGraph { nodes: vec![None; ALPHABET_SIZE] };
+/// Build a graph from a list of constraints between `num_nodes` nodes.
+fn build_graph(constraints: &[Constraint], num_nodes: usize) -> SimpleGraph {
+    let mut graph = SimpleGraph {
+        nodes: vec![
+            Node {
+                children: vec![],
+                num_parents: 0,
+            };
+            num_nodes
+        ],
+    };
     for c in constraints {
         graph.get_mut_node(c.0).children.push(c.1);
         graph.get_mut_node(c.1).num_parents += 1;
@@ -132,23 +345,22 @@ where
     /// The original node is not mutable.
     fn get_mut_node(&mut self, key: NodeId) -> (&Node, &mut T) {
         (
-            &self.graph.get_node(key),
-            &mut self.annotations[key.0 as usize],
+            self.graph.get_node(key),
+            &mut self.annotations[key.0],
         )
     }
     /// Get the node of the original graph, with the associated metadata.
     fn get_node(&self, key: NodeId) -> (&Node, &T) {
-        (&self.graph.get_node(key), &self.annotations[key.0 as usize])
+        (self.graph.get_node(key), &self.annotations[key.0])
     }
 }
 
 /// Find all the roots (no parent) of a simple graph.
-#[allow(clippy::cast_possible_truncation)]
 fn find_roots(graph: &SimpleGraph) -> Vec<NodeId> {
     graph
         .iter()
         .enumerate()
-        .filter_map(|(i, n)| (n.num_parents == 0).as_some(NodeId(i as u8)))
+        .filter_map(|(i, n)| (n.num_parents == 0).as_some(NodeId(i)))
         .collect()
 }
 
@@ -173,7 +385,7 @@ fn mark_node_as_finished(
 }
 
 /// From an annotated graph, find the build order: for each step, find the node with no dependency
-/// with the lowest value (i.e. 'A' before 'B').
+/// with the lowest value (i.e. the first name in lexicographic order, 'A' before 'B').
 fn find_build_order_from_graph(mut graph: AnnotatedGraph<usize>) -> Vec<NodeId> {
     // The BinaryHeap returns the max. Since Ord for NodeId returns the opposite order, this is a
     // min heap, initialized with all the nodes without dependencies.
@@ -187,16 +399,30 @@ fn find_build_order_from_graph(mut graph: AnnotatedGraph<usize>) -> Vec<NodeId>
     result
 }
 
-/// Returns the time it takes to complete a step. It is defined as 60 + the position of the letter,
-/// so 61 for 'A', 62 for 'B', etc.
-fn get_node_time(node: NodeId) -> usize {
-    node.0 as usize + 61
+/// The time it takes to complete a step in the puzzle, in seconds: 60 + the position of the
+/// letter, so 61 for 'A', 62 for 'B', etc.
+///
+/// For longer names, the positions of all the letters (in any case) are added, and the other
+/// characters don't count.
+///
+/// For example:
+/// ```
+/// assert_eq!(61, lib::step_time("A"));
+/// assert_eq!(60 + 2 + 3, lib::step_time("b-c"));
+/// ```
+pub fn step_time(name: &str) -> usize {
+    60 + name
+        .bytes()
+        .filter(u8::is_ascii_alphabetic)
+        .map(|b| (b.to_ascii_uppercase() - b'A') as usize + 1)
+        .sum::<usize>()
 }
 
 /// Given that we have several workers that can process steps in parallel, this returns the total
-/// time to process the graph.
+/// time to process the graph, with the time of each step indexed by `NodeId`.
 fn find_build_time_from_graph_with_workers(
     mut graph: AnnotatedGraph<usize>,
+    node_times: &[usize],
     num_workers: usize,
 ) -> usize {
     // Nodes without dependencies.
@@ -208,7 +434,7 @@ fn find_build_time_from_graph_with_workers(
     // time (from the start of the build) at which it will be done.
     // Initialize the workers with the top n steps from the heap (as much as possible).
     let mut workers: Vec<Option<(usize, NodeId)>> = (0..num_workers)
-        .map(|_| node_heap.pop().map(|n| (get_node_time(n), n)))
+        .map(|_| node_heap.pop().map(|n| (node_times[n.0], n)))
         .collect();
 
     // While the workers are still doing something, find the next step completion time.
@@ -228,38 +454,171 @@ fn find_build_time_from_graph_with_workers(
             .map(|o| if o.is_some() && o.unwrap().0 != total_time {
                 *o
             } else {
-                node_heap.pop().map(|n| (total_time + get_node_time(n), n))
+                node_heap.pop().map(|n| (total_time + node_times[n.0], n))
             })
             .collect();
     }
     total_time
 }
 
-pub fn find_build_order(lines: &[String]) -> Vec<NodeId> {
-    let graph = build_graph(&parse_constraints(lines));
-    let annotated_graph = AnnotatedGraph::new(graph);
-    find_build_order_from_graph(annotated_graph)
+/// Find the order of the steps given by the constraints in `lines`, with a single worker: among
+/// the steps that can begin, the first name in lexicographic order comes first.
+///
+/// For example:
+/// ```
+/// let lines: Vec<String> = vec![
+///     "Step compile-core must be finished before step link can begin.",
+///     "Step compile-app must be finished before step link can begin.",
+///     "Step link must be finished before step test can begin.",
+/// ]
+/// .into_iter()
+/// .map(String::from)
+/// .collect();
+/// assert_eq!(
+///     vec!["compile-app", "compile-core", "link", "test"],
+///     lib::find_build_order(&lines).unwrap()
+/// );
+/// ```
+pub fn find_build_order(lines: &[String]) -> Result<Vec<String>, BuildError> {
+    Ok(Instructions::parse(lines)?.build_order()?)
 }
 
-pub fn find_build_time_with_workers(lines: &[String], num_workers: usize) -> usize {
-    let graph = build_graph(&parse_constraints(lines));
-    let annotated_graph = AnnotatedGraph::new(graph);
-    find_build_time_from_graph_with_workers(annotated_graph, num_workers)
+/// Find the time to complete the steps given by the constraints in `lines` with several workers,
+/// with the time of each step given by `step_time`.
+pub fn find_build_time_with_workers(
+    lines: &[String],
+    num_workers: usize,
+) -> Result<usize, BuildError> {
+    Ok(Instructions::parse(lines)?.build_time(num_workers, step_time)?)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_node_id() {
-        assert_eq!(node_id("A").unwrap().1, NodeId(0));
-        assert_eq!(node_id("B").unwrap().1, NodeId(1));
-        assert_eq!(node_id("Z").unwrap().1, NodeId(25));
+    fn example() -> Vec<String> {
+        vec![
+            "Step C must be finished before step A can begin.",
+            "Step C must be finished before step F can begin.",
+            "Step A must be finished before step B can begin.",
+            "Step A must be finished before step D can begin.",
+            "Step B must be finished before step E can begin.",
+            "Step D must be finished before step E can begin.",
+            "Step F must be finished before step E can begin.",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect()
     }
+
     #[test]
     fn test_constraint() {
         assert_eq!(constraint("Step F must be finished before step E can begin.").unwrap().1,
-                   (NodeId(5), NodeId(4)));
+                   ("F", "E"));
+        assert_eq!(
+            constraint("Step compile core must be finished before step link-2 can begin.")
+                .unwrap()
+                .1,
+            ("compile core", "link-2")
+        );
+    }
+
+    #[test]
+    fn test_step_time() {
+        assert_eq!(61, step_time("A"));
+        assert_eq!(86, step_time("Z"));
+        assert_eq!(60 + 2 + 3, step_time("b-c"));
+    }
+
+    #[test]
+    fn test_find_build_order() {
+        let order = find_build_order(&example()).unwrap();
+        assert_eq!("CABDFE", order.concat());
+    }
+
+    #[test]
+    fn test_many_steps() {
+        // A chain of steps whose numeric order differs from their lexicographic order, and a
+        // step that every one of them unlocks.
+        let mut lines = Vec::new();
+        for i in 0..300 {
+            lines.push(format!(
+                "Step s{} must be finished before step final can begin.",
+                i
+            ));
+        }
+        let order = find_build_order(&lines).unwrap();
+        assert_eq!(301, order.len());
+        assert_eq!(vec!["s0", "s1", "s10", "s100"], order[..4].to_vec());
+        assert_eq!("s99", order[299]);
+        assert_eq!("final", order[300]);
+    }
+
+    #[test]
+    fn test_find_build_time_with_workers() {
+        // With one worker, the times add up.
+        let total: usize = "ABCDEF".bytes().map(|b| (b - b'A') as usize + 61).sum();
+        assert_eq!(Ok(total), find_build_time_with_workers(&example(), 1));
+        assert_eq!(Ok(258), find_build_time_with_workers(&example(), 2));
+    }
+
+    #[test]
+    fn test_malformed_lines() {
+        for line in &[
+            "Step C must be finished before step A.",
+            "Step C must be finished before step A can begin. Or not.",
+            "Step  must be finished before step A can begin.",
+            "C -> A",
+        ] {
+            let mut lines = example();
+            lines.insert(2, String::new());
+            lines.insert(4, line.to_string());
+            assert_eq!(
+                Err(BuildError::Parse(ParseError {
+                    line: 5,
+                    text: line.to_string(),
+                })),
+                find_build_order(&lines)
+            );
+        }
+        let lines = vec!["Step C must be finished before step A".to_string()];
+        assert_eq!(
+            "line 1: invalid constraint \"Step C must be finished before step A\"",
+            find_build_time_with_workers(&lines, 2).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_cycles() {
+        let mut lines = example();
+        lines.push("Step E must be finished before step A can begin.".to_string());
+        lines.push("Step E must be finished before step G can begin.".to_string());
+        let error = CycleError {
+            steps: vec!["A", "B", "D", "E", "G"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        };
+        assert_eq!(Err(BuildError::Cycle(error.clone())), find_build_order(&lines));
+        assert_eq!(
+            Err(BuildError::Cycle(error)),
+            find_build_time_with_workers(&lines, 2)
+        );
+        // A step that must be finished before itself.
+        let lines = vec!["Step A must be finished before step A can begin.".to_string()];
+        assert_eq!(
+            "cyclic constraints, these steps can never begin: A",
+            find_build_order(&lines).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_instructions() {
+        let instructions = Instructions::parse(&example()).unwrap();
+        assert_eq!(6, instructions.steps().len());
+        assert_eq!(Ok(6), instructions.build_time(1, |_| 1));
+        // C, then A and F, then B and D, then E.
+        assert_eq!(Ok(4), instructions.build_time(2, |_| 1));
+        assert_eq!(Ok(15), instructions.build_time(2, |name| step_time(name) - 60));
     }
 }
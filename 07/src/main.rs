@@ -3,19 +3,25 @@ use std::io;
 use std::io::BufRead;
 use std::string::String;
 
-fn main() {
-    let stdin = io::stdin();
-    let lines: Vec<String> = stdin.lock().lines().map(|l| l.unwrap()).collect();
-    println!(
-        "Single worker build steps: {}",
-        lib::find_build_order(&lines)
-            .iter()
-            .map(|&n| (u8::from(n) + b'A') as char)
-            .collect::<String>()
-    );
+/// Print the order of the steps with a single worker, and the build time with 5 workers.
+fn run(lines: &[String]) -> Result<(), lib::BuildError> {
+    let instructions = lib::Instructions::parse(lines)?;
+    let order = instructions.build_order()?;
+    // Single letters are written together, as in the puzzle, longer names are separated.
+    let separator = if order.iter().all(|n| n.chars().count() == 1) { "" } else { " " };
+    println!("Single worker build steps: {}", order.join(separator));
     println!(
         "5 worker build time: {}",
-        lib::find_build_time_with_workers(&lines, 5)
+        instructions.build_time(5, lib::step_time)?
     );
+    Ok(())
+}
 
+fn main() {
+    let stdin = io::stdin();
+    let lines: Vec<String> = stdin.lock().lines().map(|l| l.unwrap()).collect();
+    if let Err(err) = run(&lines) {
+        println!("Error reading the constraints: {}", err);
+        std::process::exit(2);
+    }
 }